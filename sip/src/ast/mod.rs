@@ -1,15 +1,20 @@
-use crate::{
-    parser,
-    tokens::{self, Token},
-};
+use crate::span::Span;
+use crate::tokens::{self, Token};
 use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Node {
     Identifier(tokens::Token),
     VarStmt(VarStmt),                     // var x = value
+    Destructure(Destructure),             // var (x, y) = tuple
     Assign(Assign),                       // x = value
+    CompoundAssign(CompoundAssign),       // x += value, xs[i] -= value, p.x *= value
     IfStmt(Rc<Node>, Rc<Node>, Rc<Node>), // cond, then, elseThen
+    While(While),                         // while (cond) body
+    For(For),                             // for (init; cond; step) body
+    ForIn(ForIn),                         // for x in iterable body
+    Break,
+    Continue,
 
     // left, operator, right
     Logical(Rc<Node>, tokens::Token, Rc<Node>),
@@ -19,13 +24,38 @@ pub enum Node {
 
     // op, value, -1, !true
     Unary(Unary),
-    ExpressionStmt(Rc<Node>),
     // interger, string, true, false, etc
     Literal(tokens::Token),
     Group(Rc<Node>),
+    Interpolation(Vec<Node>), // "a ${b} c", text parts are string literals
+    Tuple(Vec<Node>),         // (a, b, c)
+    List(Vec<Node>),          // [a, b, c]
+    Map(Vec<(Node, Node)>),   // {key: value, ...}
+    Index(Index),             // value[index]
+    SetIndex(SetIndex),       // value[index] = new value
+    FuncDecl(FuncDecl),       // def name(a, b) { ... }
+    Lambda(Lambda),           // def (a, b) { ... }, (a, b) => a + b
+    ClassDecl(ClassDecl),     // class Name < Parent { def method() { ... } }
+    Get(Get),                 // object.name
+    Set(Set),                 // object.name = value
+    This,
+    Super(Token), // super.method
+    Call(Call),   // callee(x, y)
     Return(Return),
+    Print(Vec<Node>), // print(a, b, ...)
     Block(Vec<Node>),
     Null,
+    Spanned(Span, Rc<Node>), // where the node came from in the source
+}
+
+impl Node {
+    // the node under any position wrappers, for code that looks at its shape
+    pub fn unspanned(&self) -> &Node {
+        match self {
+            Node::Spanned(_, inner) => inner.unspanned(),
+            _ => self,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -37,7 +67,22 @@ pub struct VarStmt {
 impl VarStmt {
     pub fn new(name: Token, value: Node) -> Self {
         Self {
-            name: name,
+            name,
+            value: Rc::new(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Destructure {
+    pub names: Vec<Token>,
+    pub value: Rc<Node>,
+}
+
+impl Destructure {
+    pub fn new(names: Vec<Token>, value: Node) -> Self {
+        Self {
+            names,
             value: Rc::new(value),
         }
     }
@@ -52,59 +97,59 @@ pub struct Assign {
 impl Assign {
     pub fn new(name: Token, value: Node) -> Self {
         Self {
-            name: name,
+            name,
             value: Rc::new(value),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct IfStmt {
+pub struct While {
     pub cond: Rc<Node>,
-    pub else_stmt: Rc<Node>,
-    pub then_stmt: Rc<Node>,
+    pub body: Rc<Node>,
 }
 
-impl IfStmt {
-    pub fn new(cond: Node, else_stmt: Node, then_stmt: Node) -> Self {
+impl While {
+    pub fn new(cond: Node, body: Node) -> Self {
         Self {
             cond: Rc::new(cond),
-            else_stmt: Rc::new(else_stmt),
-            then_stmt: Rc::new(then_stmt),
+            body: Rc::new(body),
         }
     }
 }
 
-#[derive(Clone)]
-pub struct Logical {
-    pub left: Rc<Node>,
-    pub op: Token,
-    pub right: Rc<Node>,
+#[derive(Clone, Debug, PartialEq)]
+pub struct For {
+    pub init: Rc<Node>,
+    pub cond: Rc<Node>,
+    pub step: Rc<Node>,
+    pub body: Rc<Node>,
 }
 
-impl Logical {
-    pub fn new(left: Node, op: Token, right: Node) -> Self {
+impl For {
+    pub fn new(init: Node, cond: Node, step: Node, body: Node) -> Self {
         Self {
-            left: Rc::new(left),
-            op,
-            right: Rc::new(right),
+            init: Rc::new(init),
+            cond: Rc::new(cond),
+            step: Rc::new(step),
+            body: Rc::new(body),
         }
     }
 }
 
-#[derive(Clone)]
-pub struct Binary {
-    pub op: Token,
-    pub left: Rc<Node>,
-    pub right: Rc<Node>,
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForIn {
+    pub name: Token,
+    pub iterable: Rc<Node>,
+    pub body: Rc<Node>,
 }
 
-impl Binary {
-    pub fn new(left: Node, op: Token, right: Node) -> Self {
+impl ForIn {
+    pub fn new(name: Token, iterable: Node, body: Node) -> Self {
         Self {
-            op,
-            left: Rc::new(left),
-            right: Rc::new(right),
+            name,
+            iterable: Rc::new(iterable),
+            body: Rc::new(body),
         }
     }
 }
@@ -118,61 +163,168 @@ pub struct Unary {
 impl Unary {
     pub fn new(op: Token, right: Node) -> Self {
         Self {
-            op: op,
+            op,
             right: Rc::new(right),
         }
     }
 }
 
-#[derive(Clone)]
-pub struct Literal {
-    pub literal: Token,
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuncDecl {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Rc<Node>,
 }
 
-impl Literal {
-    pub fn new(l: Token) -> Self {
-        Self { literal: l }
+impl FuncDecl {
+    pub fn new(name: Token, params: Vec<Token>, body: Node) -> Self {
+        Self {
+            name,
+            params,
+            body: Rc::new(body),
+        }
     }
 }
 
-#[derive(Clone)]
-pub struct Group {
-    pub stmt: Rc<Node>,
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDecl {
+    pub name: Token,
+    pub superclass: Rc<Node>, // Node::Null when there is no parent class
+    pub methods: Vec<FuncDecl>,
 }
 
-impl Group {
-    pub fn new(stmt: Node) -> Self {
+impl ClassDecl {
+    pub fn new(name: Token, superclass: Node, methods: Vec<FuncDecl>) -> Self {
         Self {
-            stmt: Rc::new(stmt),
+            name,
+            superclass: Rc::new(superclass),
+            methods,
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Return {
+pub struct Get {
+    pub object: Rc<Node>,
+    pub name: Token,
+}
+
+impl Get {
+    pub fn new(object: Node, name: Token) -> Self {
+        Self {
+            object: Rc::new(object),
+            name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Set {
+    pub object: Rc<Node>,
+    pub name: Token,
     pub value: Rc<Node>,
 }
 
-impl Return {
-    pub fn new(value: Node) -> Self {
+impl Set {
+    pub fn new(object: Node, name: Token, value: Node) -> Self {
         Self {
+            object: Rc::new(object),
+            name,
             value: Rc::new(value),
         }
     }
 }
 
-#[derive(Clone)]
-pub struct Block {
-    pub stmts: Vec<Node>,
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lambda {
+    pub params: Vec<Token>,
+    pub body: Rc<Node>,
 }
 
-impl Block {
-    pub fn new(stmts: Vec<Node>) -> Self {
-        let mut bs = vec![];
-        for stmt in stmts {
-            bs.push(stmt)
+impl Lambda {
+    pub fn new(params: Vec<Token>, body: Node) -> Self {
+        Self {
+            params,
+            body: Rc::new(body),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub callee: Rc<Node>,
+    pub args: Vec<Node>,
+}
+
+impl Call {
+    pub fn new(callee: Node, args: Vec<Node>) -> Self {
+        Self {
+            callee: Rc::new(callee),
+            args,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+    pub value: Rc<Node>,
+    pub index: Rc<Node>,
+}
+
+impl Index {
+    pub fn new(value: Node, index: Node) -> Self {
+        Self {
+            value: Rc::new(value),
+            index: Rc::new(index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetIndex {
+    pub object: Rc<Node>,
+    pub index: Rc<Node>,
+    pub value: Rc<Node>,
+}
+
+impl SetIndex {
+    pub fn new(object: Node, index: Node, value: Node) -> Self {
+        Self {
+            object: Rc::new(object),
+            index: Rc::new(index),
+            value: Rc::new(value),
+        }
+    }
+}
+
+// target is an Identifier, Get or Index node, op is the compound token, e.g. +=
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompoundAssign {
+    pub target: Rc<Node>,
+    pub op: Token,
+    pub value: Rc<Node>,
+}
+
+impl CompoundAssign {
+    pub fn new(target: Node, op: Token, value: Node) -> Self {
+        Self {
+            target: Rc::new(target),
+            op,
+            value: Rc::new(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Return {
+    pub value: Rc<Node>,
+}
+
+impl Return {
+    pub fn new(value: Node) -> Self {
+        Self {
+            value: Rc::new(value),
         }
-        Self { stmts: bs }
     }
 }
 
@@ -0,0 +1,453 @@
+use crate::{
+    errors::{EvalError, LexerError, ParserError},
+    object::Object,
+    span::Span,
+    tokens::Token,
+};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+const ESCAPES_HELP: &str =
+    "the escapes are \\n \\t \\r \\0 \\\\ \\\" \\$ and \\u{...}, or use a raw string r\"...\"";
+
+// an error as it is shown to the user: what went wrong, where, and how to fix it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(message: String) -> Self {
+        Self {
+            message,
+            span: None,
+            help: vec![],
+        }
+    }
+
+    pub fn with_span(mut self, span: &Span) -> Self {
+        if span.is_known() {
+            self.span = Some(span.clone());
+        }
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help.push(help);
+        self
+    }
+
+    // source is the text of the file the span points into. e.g.
+    //
+    // error: cannot find `cont` in this scope
+    //  --> main.sip:2:7
+    //   |
+    // 2 | print(cont)
+    //   |       ^^^^
+    //   = help: a similar name exists: `count`
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let paint = |text: &str, code: &str| {
+            if color {
+                format!("{}{}{}", code, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let mut out = format!(
+            "{}{}\n",
+            paint("error", RED),
+            paint(&format!(": {}", self.message), BOLD)
+        );
+
+        let mut gutter = String::from(" ");
+        if let Some(span) = &self.span {
+            let line_no = span.line.to_string();
+            gutter = " ".repeat(line_no.len());
+            out.push_str(&format!("{}{} {}\n", gutter, paint("-->", BLUE), span));
+
+            if let Some(line) = source.lines().nth(span.line - 1) {
+                let bar = paint("|", BLUE);
+                // keep tabs so the caret lines up with the text above it
+                let indent: String = line
+                    .chars()
+                    .take(span.column - 1)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                // a span running over several lines is underlined to the end of its first
+                let width = source
+                    .get(span.offset..span.offset + span.len)
+                    .map(|s| s.chars().take_while(|c| *c != '\n').count())
+                    .unwrap_or(0)
+                    .max(1);
+
+                out.push_str(&format!("{} {}\n", gutter, bar));
+                out.push_str(&format!("{} {} {}\n", paint(&line_no, BLUE), bar, line));
+                out.push_str(&format!(
+                    "{} {} {}{}\n",
+                    gutter,
+                    bar,
+                    indent,
+                    paint(&"^".repeat(width), RED)
+                ));
+            }
+        }
+
+        for help in &self.help {
+            out.push_str(&format!("{} {} {}\n", gutter, paint("= help:", CYAN), help));
+        }
+        out
+    }
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(err: &LexerError) -> Self {
+        let diagnostic = match err {
+            LexerError::InvalidToken(c, _) => {
+                Diagnostic::new(format!("unexpected character `{}`", c))
+            }
+            LexerError::InvalidString(_, _) => Diagnostic::new("unterminated string".to_string())
+                .with_help("add a closing `\"`".to_string()),
+            LexerError::InvalidEscape(esc, _) => {
+                Diagnostic::new(format!("unknown escape sequence `{}`", esc))
+                    .with_help(ESCAPES_HELP.to_string())
+            }
+            LexerError::InvalidNum(msg, _) => Diagnostic::new(format!("invalid number: {}", msg)),
+            LexerError::UnterminatedComment(_) => {
+                Diagnostic::new("unterminated block comment".to_string())
+                    .with_help("block comments nest, every `/*` needs its own `*/`".to_string())
+            }
+        };
+        diagnostic.with_span(err.span())
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(err: &ParserError) -> Self {
+        let diagnostic = match err {
+            ParserError::NotSupportedToken(Token::EOF, _) => {
+                Diagnostic::new("unexpected end of input".to_string())
+            }
+            ParserError::NotSupportedToken(tk, _) => {
+                Diagnostic::new(format!("unexpected token `{}`", tk))
+            }
+            ParserError::ExpectedTokenNotFound(msg, _) => Diagnostic::new(msg.clone()),
+            ParserError::InvalidAssignTarget(_, _) => {
+                Diagnostic::new("invalid assignment target".to_string()).with_help(
+                    "only variables, properties and index expressions can be assigned to"
+                        .to_string(),
+                )
+            }
+        };
+        diagnostic.with_span(err.span())
+    }
+}
+
+impl From<&EvalError> for Diagnostic {
+    fn from(err: &EvalError) -> Self {
+        let message = |msg: String| Diagnostic::new(msg);
+        match err {
+            EvalError::At(span, inner) => Diagnostic::from(&**inner).with_span(span),
+            EvalError::NotLiteral(tk) => message(format!("`{}` is not a literal", tk)),
+            EvalError::NotNumber(obj) => {
+                message(format!("expected a number, found {}", describe(obj)))
+            }
+            EvalError::NotIdent(tk) | EvalError::TkIsNotIdent(tk) => {
+                message(format!("`{}` is not an identifier", tk))
+            }
+            EvalError::NotNumberOrStr(obj) => message(format!(
+                "expected a number or a string, found {}",
+                describe(obj)
+            )),
+            EvalError::NotIterable(obj) => message(format!("{} is not iterable", describe(obj)))
+                .with_help(
+                    "ranges, strings, tuples, lists and maps can be looped over".to_string(),
+                ),
+            EvalError::NotIndexable(obj) => {
+                message(format!("{} can not be indexed", describe(obj)))
+            }
+            EvalError::InvalidIndex(obj) => {
+                message(format!("{} is not a valid index", describe(obj)))
+                    .with_help("indices are integers, negative ones count from the end".to_string())
+            }
+            EvalError::NotAssignable(obj) => message(format!(
+                "{} does not support item assignment",
+                describe(obj)
+            )),
+            EvalError::UnhashableKey(obj) => {
+                message(format!("{} can not be used as a map key", describe(obj))).with_help(
+                    "map keys are null, bools, integers, strings or tuples of those".to_string(),
+                )
+            }
+            EvalError::KeyNotFound(obj) => {
+                message(format!("key {} is not in the map", describe(obj)))
+            }
+            EvalError::IndexOutOfRange(idx, len) => {
+                message(format!("index {} is out of range for length {}", idx, len))
+            }
+            EvalError::DestructureMismatch(names, values) => message(format!(
+                "can not unpack {} values into {} names",
+                values, names
+            )),
+            EvalError::DifferObjectToCompare(left, right) => message(format!(
+                "can not compare {} with {}",
+                describe(left),
+                describe(right)
+            )),
+            EvalError::DivideByZero(_) => message("division by zero".to_string()),
+            EvalError::NotInteger(op, obj) => message(format!(
+                "`{}` expects integers, found {}",
+                op,
+                describe(obj)
+            )),
+            EvalError::OperandOutOfRange(op, obj) => {
+                message(format!("{} is out of range for `{}`", describe(obj), op))
+            }
+            EvalError::NotSupportedOperator(tk) => {
+                message(format!("operator `{}` is not supported", tk))
+            }
+            EvalError::InvalidOperands(op, left, right) => message(format!(
+                "can not apply `{}` to {} and {}",
+                op,
+                describe(left),
+                describe(right)
+            )),
+            EvalError::IdentNotFound(name, similar) => {
+                let diagnostic = message(format!("cannot find `{}` in this scope", name));
+                match similar {
+                    Some(similar) => {
+                        diagnostic.with_help(format!("a similar name exists: `{}`", similar))
+                    }
+                    None => diagnostic,
+                }
+            }
+            EvalError::IdentifierIsNotCallable(name) => {
+                message(format!("`{}` is not callable", name))
+                    .with_help("only functions and classes can be called".to_string())
+            }
+            EvalError::ArgumentsMismatch(name, expected, got) => message(format!(
+                "`{}` takes {} {} but {} {} given",
+                name,
+                expected,
+                plural(*expected, "argument"),
+                got,
+                if *got == 1 { "was" } else { "were" }
+            )),
+            EvalError::OnlyClassInstanceHaveProperty(name) => message(format!(
+                "`{}` has no properties, only class instances do",
+                name
+            )),
+            EvalError::UndefinedProperty(name) => {
+                message(format!("property `{}` is not defined", name))
+            }
+            EvalError::InheritFromNonClass(name) => message(format!("`{}` is not a class", name))
+                .with_help("a class can only inherit from another class".to_string()),
+            EvalError::SuperOutsideMethod => {
+                message("`super` can only be used inside a method".to_string())
+            }
+            EvalError::NoSuperclass(name) => message(format!("class `{}` has no superclass", name))
+                .with_help(format!("declare one with `class {} < Parent`", name)),
+            EvalError::OutsideLoop(tk) => message(format!("`{}` outside of a loop", tk)),
+            EvalError::RecursionLimit(max) => {
+                message(format!("recursion is deeper than {} calls", max))
+                    .with_help("check that the recursion reaches its base case".to_string())
+            }
+            EvalError::WriteOutput(msg) => message(format!("failed to write output: {}", msg)),
+            EvalError::UnknowNode(node) => message(format!("can not evaluate {:?}", node)),
+        }
+    }
+}
+
+// e.g. string "a", integer 1, <fn add>
+fn describe(obj: &Object) -> String {
+    match obj {
+        Object::Function(_) | Object::Class(_) | Object::ClassInstance(_) | Object::Null => {
+            obj.to_string()
+        }
+        Object::SString(s) => format!("string {:?}", s),
+        _ => format!("{} {}", obj.type_name(), obj),
+    }
+}
+
+fn plural(n: usize, word: &str) -> String {
+    if n == 1 {
+        word.to_string()
+    } else {
+        format!("{}s", word)
+    }
+}
+
+// the candidate closest to name by edit distance, if any is close enough to be a typo
+pub fn similar_name(name: &str, candidates: &[String]) -> Option<String> {
+    let len = name.chars().count();
+    let max_distance = (len / 3).max(1);
+    candidates
+        .iter()
+        .filter(|c| c.as_str() != name)
+        .map(|c| (edit_distance(&name.to_lowercase(), &c.to_lowercase()), c))
+        // a one letter name is one edit away from every other one letter name
+        .filter(|(d, c)| *d <= max_distance && *d < len.min(c.chars().count()))
+        .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+        .map(|(_, c)| c.clone())
+}
+
+// levenshtein distance over chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{similar_name, Diagnostic};
+    use crate::{eval::Interpreter, lexer::Lexer, parser::Parser};
+
+    // lex, parse and run input, rendering whichever error comes first
+    fn render(input: &str) -> String {
+        let (tokens, spans) =
+            match Lexer::with_file(input.to_string(), "t.sip").scan_tokens_with_spans() {
+                Ok(v) => v,
+                Err(e) => return Diagnostic::from(&e).render(input, false),
+            };
+        let program = match Parser::with_spans(tokens, spans).parse() {
+            Ok(v) => v,
+            Err(e) => return Diagnostic::from(&e).render(input, false),
+        };
+        match Interpreter::new().eval_program(program) {
+            Ok(v) => panic!("expect an error, got {:?}", v),
+            Err(e) => Diagnostic::from(&e).render(input, false),
+        }
+    }
+
+    #[test]
+    fn test_render_diagnostics() {
+        let inputs = vec![
+            (
+                "var count = 1\nprint(cont + 1)",
+                "error: cannot find `cont` in this scope
+ --> t.sip:2:7
+  |
+2 | print(cont + 1)
+  |       ^^^^
+  = help: a similar name exists: `count`
+",
+            ),
+            (
+                "if (true {\n}",
+                "error: expect ) after if
+ --> t.sip:1:10
+  |
+1 | if (true {
+  |          ^
+",
+            ),
+            (
+                "var s = \"a\\q\"",
+                "error: unknown escape sequence `\\q`
+ --> t.sip:1:11
+  |
+1 | var s = \"a\\q\"
+  |           ^^
+  = help: the escapes are \\n \\t \\r \\0 \\\\ \\\" \\$ and \\u{...}, or use a raw string r\"...\"
+",
+            ),
+            (
+                "\tvar x = \"a\" - 1",
+                "error: can not apply `-` to string \"a\" and integer 1
+ --> t.sip:1:14
+  |
+1 | \tvar x = \"a\" - 1
+  | \t            ^
+",
+            ),
+            (
+                "def f(a) {\n  return a\n}\nf(1, 2)",
+                "error: `f` takes 1 argument but 2 were given
+ --> t.sip:4:1
+  |
+4 | f(1, 2)
+  | ^^^^^^^
+",
+            ),
+            // the end of a file that ends with a line break is on its last line
+            (
+                "print(y\n",
+                "error: expect ) after print
+ --> t.sip:1:8
+  |
+1 | print(y
+  |        ^
+",
+            ),
+            (
+                "class A < Base {}",
+                "error: cannot find `Base` in this scope
+ --> t.sip:1:11
+  |
+1 | class A < Base {}
+  |           ^^^^
+",
+            ),
+            (
+                "var B = 1\nclass A < B {}",
+                "error: `B` is not a class
+ --> t.sip:2:11
+  |
+2 | class A < B {}
+  |           ^
+  = help: a class can only inherit from another class
+",
+            ),
+        ];
+
+        for (input, expect) in inputs {
+            let got = render(input);
+            println!("{}", got);
+            assert_eq!(expect, got);
+        }
+
+        // no location, and colour only when asked for
+        let diagnostic = Diagnostic::new("boom".to_string()).with_help("try again".to_string());
+        assert_eq!(
+            "error: boom\n  = help: try again\n",
+            diagnostic.render("", false)
+        );
+        assert_eq!(
+            "\x1b[1;31merror\x1b[0m\x1b[1m: boom\x1b[0m\n  \x1b[1;36m= help:\x1b[0m try again\n",
+            diagnostic.render("", true)
+        );
+    }
+
+    #[test]
+    fn test_similar_name() {
+        let names: Vec<String> = vec!["count", "counter", "x", "Total", "print"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        assert_eq!(Some("count".to_string()), similar_name("cont", &names));
+        assert_eq!(
+            Some("counter".to_string()),
+            similar_name("counterr", &names)
+        );
+        assert_eq!(Some("Total".to_string()), similar_name("total", &names));
+        assert_eq!(None, similar_name("y", &names));
+        assert_eq!(None, similar_name("count", &names[..1]));
+        assert_eq!(None, similar_name("something", &names));
+    }
+}
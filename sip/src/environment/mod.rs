@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::object::Object;
+
+// one lexical scope, lookups walk outward through `outer`
+#[derive(Debug, PartialEq)]
+pub struct Environment {
+    store: HashMap<String, Object>,
+    outer: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self {
+            store: HashMap::new(),
+            outer: None,
+        }
+    }
+
+    pub fn new_enclosed(outer: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            store: HashMap::new(),
+            outer: Some(outer),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Object> {
+        match self.store.get(key) {
+            Some(v) => Some(v.clone()),
+            None => match self.outer {
+                Some(ref outer) => outer.borrow().get(key),
+                None => None,
+            },
+        }
+    }
+
+    // every name visible from this scope
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.store.keys().cloned().collect();
+        if let Some(ref outer) = self.outer {
+            for name in outer.borrow().names() {
+                if !self.store.contains_key(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    // declare in this scope, shadowing any outer binding
+    pub fn define(&mut self, key: String, val: Object) {
+        self.store.insert(key, val);
+    }
+
+    // update the nearest existing binding, return false if there is none
+    pub fn assign(&mut self, key: &str, val: Object) -> bool {
+        if let Some(v) = self.store.get_mut(key) {
+            *v = val;
+            return true;
+        }
+
+        match self.outer {
+            Some(ref outer) => outer.borrow_mut().assign(key, val),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Environment;
+    use crate::object::Object;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_env_shadow_and_assign() {
+        let global = Rc::new(RefCell::new(Environment::new()));
+        global
+            .borrow_mut()
+            .define("x".to_string(), Object::Integer(1));
+        global
+            .borrow_mut()
+            .define("y".to_string(), Object::Integer(2));
+
+        let mut inner = Environment::new_enclosed(global.clone());
+        inner.define("x".to_string(), Object::Integer(10));
+        assert_eq!(Some(Object::Integer(10)), inner.get("x"));
+        assert_eq!(Some(Object::Integer(2)), inner.get("y"));
+
+        assert!(inner.assign("y", Object::Integer(20)));
+        assert!(inner.assign("x", Object::Integer(100)));
+        assert!(!inner.assign("z", Object::Integer(0)));
+
+        assert_eq!(Some(Object::Integer(1)), global.borrow().get("x"));
+        assert_eq!(Some(Object::Integer(20)), global.borrow().get("y"));
+        assert_eq!(None, global.borrow().get("z"));
+    }
+}
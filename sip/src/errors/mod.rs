@@ -1,10 +1,25 @@
-use crate::{ast::Node, object::Object, tokens::Token};
+use crate::{ast::Node, object::Object, span::Span, tokens::Token};
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum LexerError {
-    InvalidToken(char),
-    InvalidString(String),
-    InvalidNum(String),
+    InvalidToken(char, Span),
+    InvalidString(String, Span), // the opening quote
+    InvalidEscape(String, Span),
+    InvalidNum(String, Span),
+    UnterminatedComment(Span), // the opening /*
+}
+
+impl LexerError {
+    pub fn span(&self) -> &Span {
+        match self {
+            LexerError::InvalidToken(_, span)
+            | LexerError::InvalidString(_, span)
+            | LexerError::InvalidEscape(_, span)
+            | LexerError::InvalidNum(_, span)
+            | LexerError::UnterminatedComment(span) => span,
+        }
+    }
 }
 
 impl std::error::Error for LexerError {}
@@ -12,17 +27,30 @@ impl std::error::Error for LexerError {}
 impl std::fmt::Display for LexerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            LexerError::InvalidToken(msg) => write!(f, "{}", msg),
-            LexerError::InvalidString(msg) => write!(f, "{}", msg),
-            LexerError::InvalidNum(msg) => write!(f, "{}", msg),
+            LexerError::InvalidToken(msg, _) => write!(f, "{}", msg),
+            LexerError::InvalidString(msg, _) => write!(f, "{}", msg),
+            LexerError::InvalidEscape(esc, _) => write!(f, "unknown escape sequence: {}", esc),
+            LexerError::InvalidNum(msg, _) => write!(f, "{}", msg),
+            LexerError::UnterminatedComment(_) => write!(f, "unterminated block comment"),
         }
     }
 }
 
 #[derive(Debug)]
 pub enum ParserError {
-    NotSupportedToken(Token),
-    ExpectedTokenNotFound(String),
+    NotSupportedToken(Token, Span),
+    ExpectedTokenNotFound(String, Span),
+    InvalidAssignTarget(Box<Node>, Span), // the = or compound operator
+}
+
+impl ParserError {
+    pub fn span(&self) -> &Span {
+        match self {
+            ParserError::NotSupportedToken(_, span)
+            | ParserError::ExpectedTokenNotFound(_, span)
+            | ParserError::InvalidAssignTarget(_, span) => span,
+        }
+    }
 }
 
 impl std::error::Error for ParserError {}
@@ -30,8 +58,11 @@ impl std::error::Error for ParserError {}
 impl std::fmt::Display for ParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParserError::NotSupportedToken(tk) => write!(f, "{:?}", tk),
-            ParserError::ExpectedTokenNotFound(s) => write!(f, "{}", s),
+            ParserError::NotSupportedToken(tk, _) => write!(f, "{:?}", tk),
+            ParserError::ExpectedTokenNotFound(s, _) => write!(f, "{}", s),
+            ParserError::InvalidAssignTarget(node, _) => {
+                write!(f, "can not assign to: {:?}", node)
+            }
         }
     }
 }
@@ -49,16 +80,62 @@ pub enum EvalError {
     NotNumber(Object),
     NotIdent(Token),
     NotNumberOrStr(Object),
-    NotTruthCond(Object),
+    NotIterable(Object),
+    NotIndexable(Object),
+    InvalidIndex(Object),
+    NotAssignable(Object),
+    UnhashableKey(Object),
+    KeyNotFound(Object),
+    IndexOutOfRange(i64, usize),       // index, length
+    DestructureMismatch(usize, usize), // names, values
     DifferObjectToCompare(Object, Object),
     DivideByZero(String),
+    NotInteger(Token, Object),        // operator, operand
+    OperandOutOfRange(Token, Object), // operator, operand
     NotSupportedOperator(Token),
+    InvalidOperands(Token, Object, Object), // operator, left, right
     TkIsNotIdent(Token),
-    IdentNotFound(String),
+    IdentNotFound(String, Option<String>), // name, a similar name that is in scope
     IdentifierIsNotCallable(String),
+    ArgumentsMismatch(String, usize, usize), // function, expected, got
     OnlyClassInstanceHaveProperty(String),
+    UndefinedProperty(String),
+    InheritFromNonClass(String),
+    SuperOutsideMethod,
+    NoSuperclass(String),
+    OutsideLoop(Token),
+    RecursionLimit(usize), // the maximum call depth
+    WriteOutput(String),
     UnknowNode(Node),
-    EmptyNode,
+    At(Span, Box<EvalError>), // where in the source the wrapped error was raised
+}
+
+impl EvalError {
+    // the innermost location is the most precise one, so an error is only placed once
+    pub fn at(self, span: &Span) -> EvalError {
+        match self {
+            EvalError::At(..) => self,
+            _ if !span.is_known() => self,
+            _ => EvalError::At(span.clone(), Box::new(self)),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            EvalError::At(span, _) => Some(span),
+            _ => None,
+        }
+    }
+
+    // the error without its location
+    #[cfg(test)]
+    pub fn into_kind(self) -> EvalError {
+        match self {
+            EvalError::At(_, err) => err.into_kind(),
+            _ => self,
+        }
+    }
 }
 
 impl std::error::Error for EvalError {}
@@ -70,17 +147,50 @@ impl std::fmt::Display for EvalError {
             EvalError::NotNumber(obj) => write!(f, "{:?} is not number", obj),
             EvalError::NotIdent(tk) => write!(f, "{:?} is not identifier", tk),
             EvalError::NotNumberOrStr(obj) => write!(f, "{:?} is not number or string", obj),
-            EvalError::NotTruthCond(obj) => write!(f, "{:?} is not truth condition", obj),
+            EvalError::NotIterable(obj) => write!(f, "{:?} is not iterable", obj),
+            EvalError::NotIndexable(obj) => write!(f, "{:?} can not be indexed", obj),
+            EvalError::InvalidIndex(obj) => write!(f, "{:?} is not a valid index", obj),
+            EvalError::NotAssignable(obj) => {
+                write!(f, "{:?} does not support item assignment", obj)
+            }
+            EvalError::UnhashableKey(obj) => write!(f, "{:?} can not be used as a map key", obj),
+            EvalError::KeyNotFound(obj) => write!(f, "key: {:?} is not found", obj),
+            EvalError::IndexOutOfRange(idx, len) => {
+                write!(f, "index {} is out of range for length {}", idx, len)
+            }
+            EvalError::DestructureMismatch(names, values) => {
+                write!(f, "can not unpack {} values into {} names", values, names)
+            }
             EvalError::DifferObjectToCompare(obj1, obj2) => {
                 write!(f, "{:?}, {:?} are different", obj1, obj2)
             }
             EvalError::DivideByZero(s) => write!(f, "{}", s),
+            EvalError::NotInteger(op, obj) => {
+                write!(f, "operator: {:?} expects integers, got {:?}", op, obj)
+            }
+            EvalError::OperandOutOfRange(op, obj) => {
+                write!(f, "{:?} is out of range for operator: {:?}", obj, op)
+            }
             EvalError::NotSupportedOperator(c) => write!(f, "operator: {:?} is not supported", c),
-            EvalError::IdentNotFound(ident) => write!(f, "identifier: {} is not found", ident),
+            EvalError::InvalidOperands(op, left, right) => {
+                write!(
+                    f,
+                    "operator: {:?} is not supported between {:?} and {:?}",
+                    op, left, right
+                )
+            }
+            EvalError::IdentNotFound(ident, _) => write!(f, "identifier: {} is not found", ident),
             EvalError::TkIsNotIdent(tk) => write!(f, "token: {:?} is not identifier", tk),
             EvalError::IdentifierIsNotCallable(ident) => {
                 write!(f, "{} is not callable(it shoud be function or xxx)", ident)
             }
+            EvalError::ArgumentsMismatch(name, expected, got) => {
+                write!(
+                    f,
+                    "{} expected {} arguments but got {}",
+                    name, expected, got
+                )
+            }
             EvalError::OnlyClassInstanceHaveProperty(s) => {
                 write!(
                     f,
@@ -88,12 +198,21 @@ impl std::fmt::Display for EvalError {
                     s
                 )
             }
+            EvalError::UndefinedProperty(name) => write!(f, "property: {} is not defined", name),
+            EvalError::InheritFromNonClass(name) => {
+                write!(f, "{} is not a class, can only inherit from a class", name)
+            }
+            EvalError::SuperOutsideMethod => write!(f, "can not use super outside of a method"),
+            EvalError::NoSuperclass(name) => {
+                write!(f, "can not use super in class: {} with no superclass", name)
+            }
+            EvalError::OutsideLoop(tk) => write!(f, "{:?} outside of loop", tk),
+            EvalError::RecursionLimit(max) => write!(f, "call depth exceeds: {}", max),
+            EvalError::WriteOutput(msg) => write!(f, "failed to write output: {}", msg),
             EvalError::UnknowNode(node) => {
                 write!(f, "unknown node: {:?}", node.clone())
             }
-            EvalError::EmptyNode => {
-                write!(f, "empty node")
-            }
+            EvalError::At(_, err) => write!(f, "{}", err),
         }
     }
 }
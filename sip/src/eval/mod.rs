@@ -1,41 +1,99 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
 use std::rc::Rc;
 
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
+
 use crate::{
-    ast::{Node, Program},
+    ast::{self, Node, Program},
+    diagnostic,
+    environment::Environment,
     errors::EvalError,
-    object::Object,
+    object::{Class, Function, Instance, MapKey, Object},
     tokens::Token,
 };
 
+// how deeply function calls can nest. every call takes Rust stack, so the thread running
+// the interpreter needs a stack big enough for this many
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+// the longest string, in bytes, that repeating a string with * may build
+const MAX_REPEAT_LEN: usize = 1 << 28;
+
+// the most bits an integer built by ** or << may have
+const MAX_INT_BITS: u64 = 1 << 20;
+
 pub struct Interpreter {
-    env: HashMap<String, Object>,
+    env: Rc<RefCell<Environment>>,
+    out: Box<dyn Write>, // where print writes to
+    depth: usize,        // how many function calls are in progress
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        Self::with_output(Box::new(std::io::stdout()))
+    }
+
+    pub fn with_output(out: Box<dyn Write>) -> Self {
         Self {
-            env: HashMap::new(),
+            env: Rc::new(RefCell::new(Environment::new())),
+            out,
+            depth: 0,
         }
     }
 
     fn set_value(&mut self, key: String, val: Object) {
-        self.env.insert(key, val);
+        self.env.borrow_mut().define(key, val);
+    }
+
+    pub fn get_value(&self, key: String) -> Option<Object> {
+        self.env.borrow().get(&key)
     }
-    pub fn get_value(&self, key: String) -> Option<&Object> {
-        self.env.get(&key)
+
+    // run f with env as the current scope, the previous scope is restored even on error
+    fn eval_in_scope<F>(&mut self, env: Environment, f: F) -> Result<Object, EvalError>
+    where
+        F: FnOnce(&mut Self) -> Result<Object, EvalError>,
+    {
+        let previous = std::mem::replace(&mut self.env, Rc::new(RefCell::new(env)));
+        let res = f(self);
+        self.env = previous;
+        res
+    }
+
+    fn new_scope(&self) -> Environment {
+        Environment::new_enclosed(self.env.clone())
     }
 
     pub fn eval_program(&mut self, program: Program) -> Result<Object, EvalError> {
         let mut r = Object::Null;
         for node in program.stmts {
             r = self.eval(node)?;
+            match r {
+                // a top level return ends the script with its value
+                Object::Return(val) => return Ok((*val).clone()),
+                Object::Break => return Err(EvalError::OutsideLoop(Token::Break)),
+                Object::Continue => return Err(EvalError::OutsideLoop(Token::Continue)),
+                _ => {}
+            }
         }
         Ok(r)
     }
 
     pub fn eval(&mut self, node: Node) -> Result<Object, EvalError> {
-        match node {
+        // unwrap the spans here instead of evaluating Node::Spanned recursively, which
+        // would cost a stack frame per node. the innermost span is the one reported
+        let mut node = node;
+        let mut span = None;
+        while let Node::Spanned(s, inner) = node {
+            span = Some(s);
+            node = (*inner).clone();
+        }
+
+        let result = match node {
             Node::Null => Ok(Object::Null),
             Node::Literal(tk) => self.eval_literal(tk),
             Node::Unary(unary) => {
@@ -46,34 +104,110 @@ impl Interpreter {
                 let node = (*var_stmt.value).clone();
                 self.eval_var_stmt(var_stmt.name, node)
             }
+            Node::Destructure(destructure) => {
+                let node = (*destructure.value).clone();
+                self.eval_destructure(destructure.names, node)
+            }
             Node::IfStmt(cond, then_stmt, else_stmt) => {
                 let cond_node = (*cond).clone();
                 let then_node = (*then_stmt).clone();
                 let else_node = (*else_stmt).clone();
                 self.eval_if(cond_node, then_node, else_node)
             }
+            Node::While(while_stmt) => {
+                let cond_node = (*while_stmt.cond).clone();
+                let body_node = (*while_stmt.body).clone();
+                self.eval_while(cond_node, body_node)
+            }
+            Node::For(for_stmt) => {
+                let init_node = (*for_stmt.init).clone();
+                let cond_node = (*for_stmt.cond).clone();
+                let step_node = (*for_stmt.step).clone();
+                let body_node = (*for_stmt.body).clone();
+                self.eval_for(init_node, cond_node, step_node, body_node)
+            }
+            Node::ForIn(for_in) => {
+                let iter_node = (*for_in.iterable).clone();
+                let body_node = (*for_in.body).clone();
+                self.eval_for_in(for_in.name, iter_node, body_node)
+            }
+            Node::Break => Ok(Object::Break),
+            Node::Continue => Ok(Object::Continue),
             Node::Block(stmts) => self.eval_block(stmts),
             Node::Identifier(x) => self.eval_identifier(x),
+            Node::Group(exp) => self.eval((*exp).clone()),
+            Node::Interpolation(parts) => self.eval_interpolation(parts),
+            Node::Tuple(items) => self.eval_tuple(items),
+            Node::List(items) => self.eval_list(items),
+            Node::Map(entries) => self.eval_map(entries),
+            Node::Index(index) => {
+                let value = (*index.value).clone();
+                let idx = (*index.index).clone();
+                self.eval_index(value, idx)
+            }
+            Node::SetIndex(set) => {
+                let object = (*set.object).clone();
+                let index = (*set.index).clone();
+                let value = (*set.value).clone();
+                self.eval_set_index(object, index, value)
+            }
             Node::Assign(assign) => {
                 let node: Node = (*assign.value).clone();
                 self.eval_assign(assign.name, node)
             }
+            Node::CompoundAssign(assign) => {
+                let target = (*assign.target).clone();
+                let value = (*assign.value).clone();
+                self.eval_compound_assign(target, assign.op, value)
+            }
+            Node::Logical(left, tk, right) => {
+                let ln = (*left).clone();
+                let rn = (*right).clone();
+                self.eval_logical(ln, tk, rn)
+            }
             Node::Binary(left, tk, right) => {
                 let ln = (*left).clone();
                 let rn = (*right).clone();
                 self.eval_binary(ln, tk, rn)
             }
+            Node::FuncDecl(func) => self.eval_func_decl(func.name, func.params, func.body),
+            Node::Lambda(lambda) => self.eval_lambda(lambda.params, lambda.body),
+            Node::ClassDecl(class) => {
+                let superclass = (*class.superclass).clone();
+                self.eval_class_decl(class.name, superclass, class.methods)
+            }
+            Node::Get(get) => {
+                let object = (*get.object).clone();
+                self.eval_get(object, get.name)
+            }
+            Node::Set(set) => {
+                let object = (*set.object).clone();
+                let value = (*set.value).clone();
+                self.eval_set(object, set.name, value)
+            }
+            Node::This => self.eval_identifier(Token::Ident("this".to_string())),
+            Node::Super(method) => self.eval_super(method),
+            Node::Call(call) => {
+                let callee = (*call.callee).clone();
+                self.eval_call(callee, call.args)
+            }
+            Node::Print(args) => self.eval_print(args),
             Node::Return(ret) => {
                 let node = (*ret.value).clone();
                 self.eval_return(node)
             }
-            _ => Err(EvalError::UnknowNode(node)),
+            Node::Spanned(..) => unreachable!("spans are unwrapped above"),
+        };
+        match span {
+            Some(span) => result.map_err(|e| e.at(&span)),
+            None => result,
         }
     }
 
     fn eval_literal(&self, tk: Token) -> Result<Object, EvalError> {
         match tk {
             Token::Integer(v) => Ok(Object::Integer(v)),
+            Token::BigInteger(v) => Ok(Object::BigInt(v)),
             Token::Float(v) => Ok(Object::Float(v)),
             Token::SString(v) => Ok(Object::SString(v)),
             Token::True => Ok(Object::Bool(true)),
@@ -86,19 +220,28 @@ impl Interpreter {
         match tk {
             Token::Bang => {
                 let value = self.eval(node)?;
-                match value {
-                    Object::Bool(v) => Ok(Object::Bool(!v)),
-                    _ => Err(EvalError::NotLiteral(tk)),
-                }
+                Ok(Object::Bool(!value.is_truthy()))
             }
             Token::Minus(_) => {
                 let value = self.eval(node)?;
                 match value {
-                    Object::Integer(v) => Ok(Object::Integer(-v)),
+                    Object::Integer(v) => match v.checked_neg() {
+                        Some(n) => Ok(Object::Integer(n)),
+                        None => Ok(Object::BigInt(-BigInt::from(v))),
+                    },
+                    Object::BigInt(v) => Ok(Object::from_bigint(-v)),
                     Object::Float(v) => Ok(Object::Float(-v)),
                     _ => Err(EvalError::NotLiteral(tk)),
                 }
             }
+            Token::BitNot => {
+                let value = self.eval(node)?;
+                match value {
+                    Object::Integer(v) => Ok(Object::Integer(!v)),
+                    Object::BigInt(v) => Ok(Object::from_bigint(!v)),
+                    _ => Err(EvalError::NotInteger(tk, value)),
+                }
+            }
             _ => Err(EvalError::NotLiteral(tk)),
         }
     }
@@ -114,72 +257,469 @@ impl Interpreter {
         }
     }
 
+    fn eval_destructure(&mut self, names: Vec<Token>, value: Node) -> Result<Object, EvalError> {
+        let obj = self.eval(value)?;
+        let items = match obj {
+            Object::Tuple(ref items) => items.clone(),
+            Object::List(ref items) => items.borrow().clone(),
+            _ => return Err(EvalError::DestructureMismatch(names.len(), 1)),
+        };
+        if items.len() != names.len() {
+            return Err(EvalError::DestructureMismatch(names.len(), items.len()));
+        }
+
+        for (name, item) in names.into_iter().zip(items) {
+            match name {
+                Token::Ident(v) => self.set_value(v, item),
+                _ => return Err(EvalError::NotIdent(name)),
+            }
+        }
+        Ok(obj)
+    }
+
     fn eval_if(&mut self, cond: Node, then: Node, else_node: Node) -> Result<Object, EvalError> {
         let truth = self.eval(cond)?;
-        match truth {
-            Object::Bool(v) => {
-                if v {
-                    self.eval(then)
-                } else {
-                    self.eval(else_node)
-                }
+        if truth.is_truthy() {
+            self.eval(then)
+        } else {
+            self.eval(else_node)
+        }
+    }
+
+    fn eval_while(&mut self, cond: Node, body: Node) -> Result<Object, EvalError> {
+        loop {
+            if !self.eval(cond.clone())?.is_truthy() {
+                break;
+            }
+
+            // break and continue unwind through nested blocks and ifs up to here,
+            // return keeps unwinding to the enclosing function
+            match self.eval(body.clone())? {
+                Object::Break => break,
+                ret @ Object::Return(_) => return Ok(ret),
+                _ => {}
+            }
+        }
+
+        Ok(Object::Null)
+    }
+
+    fn eval_for(
+        &mut self,
+        init: Node,
+        cond: Node,
+        step: Node,
+        body: Node,
+    ) -> Result<Object, EvalError> {
+        // the variable declared in init only lives as long as the loop
+        let scope = self.new_scope();
+        self.eval_in_scope(scope, |intpter| {
+            intpter.eval_for_loop(init, cond, step, body)
+        })
+    }
+
+    fn eval_for_loop(
+        &mut self,
+        init: Node,
+        cond: Node,
+        step: Node,
+        body: Node,
+    ) -> Result<Object, EvalError> {
+        self.eval(init)?;
+        loop {
+            if cond != Node::Null && !self.eval(cond.clone())?.is_truthy() {
+                break;
+            }
+
+            match self.eval(body.clone())? {
+                Object::Break => break,
+                ret @ Object::Return(_) => return Ok(ret),
+                _ => {}
+            }
+
+            self.eval(step.clone())?;
+        }
+
+        Ok(Object::Null)
+    }
+
+    fn eval_for_in(
+        &mut self,
+        name: Token,
+        iterable: Node,
+        body: Node,
+    ) -> Result<Object, EvalError> {
+        let ident = match name {
+            Token::Ident(ident) => ident,
+            _ => return Err(EvalError::NotIdent(name)),
+        };
+
+        let obj = self.eval(iterable)?;
+        let items = self.eval_iterable(obj)?;
+
+        for item in items {
+            // every iteration gets a fresh binding of the loop variable
+            let mut scope = self.new_scope();
+            scope.define(ident.clone(), item);
+            match self.eval_in_scope(scope, |intpter| intpter.eval(body.clone()))? {
+                Object::Break => break,
+                ret @ Object::Return(_) => return Ok(ret),
+                _ => {}
             }
-            _ => Err(EvalError::NotTruthCond(truth)),
+        }
+
+        Ok(Object::Null)
+    }
+
+    // every value that `for x in` can walk over
+    fn eval_iterable(&self, obj: Object) -> Result<Box<dyn Iterator<Item = Object>>, EvalError> {
+        match obj {
+            Object::Range(start, end) => Ok(Box::new((start..end).map(Object::Integer))),
+            Object::Tuple(items) => Ok(Box::new(items.into_iter())),
+            // iterate over a snapshot so the loop body can change the list
+            Object::List(items) => Ok(Box::new(items.borrow().clone().into_iter())),
+            Object::Map(entries) => {
+                let keys: Vec<Object> = entries.borrow().keys().map(MapKey::to_object).collect();
+                Ok(Box::new(keys.into_iter()))
+            }
+            Object::SString(s) => {
+                let chars: Vec<Object> =
+                    s.chars().map(|c| Object::SString(c.to_string())).collect();
+                Ok(Box::new(chars.into_iter()))
+            }
+            _ => Err(EvalError::NotIterable(obj)),
         }
     }
 
     fn eval_block(&mut self, stms: Vec<Node>) -> Result<Object, EvalError> {
+        let scope = self.new_scope();
+        self.eval_in_scope(scope, |intpter| intpter.eval_stmts(stms))
+    }
+
+    fn eval_stmts(&mut self, stms: Vec<Node>) -> Result<Object, EvalError> {
         let mut obj = Object::Null;
         for stmt in stms {
             obj = self.eval(stmt)?;
+            if let Object::Return(_) | Object::Break | Object::Continue = obj {
+                break;
+            }
         }
 
         Ok(obj)
     }
 
+    fn eval_tuple(&mut self, items: Vec<Node>) -> Result<Object, EvalError> {
+        let mut objs: Vec<Object> = vec![];
+        for item in items {
+            objs.push(self.eval(item)?);
+        }
+        Ok(Object::Tuple(objs))
+    }
+
+    // each part is formatted the way print shows it
+    fn eval_interpolation(&mut self, parts: Vec<Node>) -> Result<Object, EvalError> {
+        let mut res = String::new();
+        for part in parts {
+            let obj = self.eval(part)?;
+            res.push_str(&obj.to_string());
+        }
+        Ok(Object::SString(res))
+    }
+
+    fn eval_list(&mut self, items: Vec<Node>) -> Result<Object, EvalError> {
+        let mut objs: Vec<Object> = vec![];
+        for item in items {
+            objs.push(self.eval(item)?);
+        }
+        Ok(Object::List(Rc::new(RefCell::new(objs))))
+    }
+
+    fn eval_map(&mut self, entries: Vec<(Node, Node)>) -> Result<Object, EvalError> {
+        let mut map = BTreeMap::new();
+        for (key, value) in entries {
+            let key_obj = self.eval(key)?;
+            let key = self.map_key(key_obj)?;
+            map.insert(key, self.eval(value)?);
+        }
+        Ok(Object::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn map_key(&self, key: Object) -> Result<MapKey, EvalError> {
+        match MapKey::from_object(&key) {
+            Some(k) => Ok(k),
+            None => Err(EvalError::UnhashableKey(key)),
+        }
+    }
+
+    fn eval_index(&mut self, value: Node, index: Node) -> Result<Object, EvalError> {
+        let obj = self.eval(value)?;
+        let idx_obj = self.eval(index)?;
+        self.index_get(obj, idx_obj)
+    }
+
+    fn index_get(&self, obj: Object, idx_obj: Object) -> Result<Object, EvalError> {
+        match obj {
+            Object::Tuple(items) => {
+                let pos = self.resolve_index(&idx_obj, items.len())?;
+                Ok(items[pos].clone())
+            }
+            Object::List(items) => {
+                let items = items.borrow();
+                let pos = self.resolve_index(&idx_obj, items.len())?;
+                Ok(items[pos].clone())
+            }
+            Object::Map(entries) => {
+                let key = self.map_key(idx_obj.clone())?;
+                match entries.borrow().get(&key) {
+                    Some(v) => Ok(v.clone()),
+                    None => Err(EvalError::KeyNotFound(idx_obj)),
+                }
+            }
+            _ => Err(EvalError::NotIndexable(obj)),
+        }
+    }
+
+    fn eval_set_index(
+        &mut self,
+        object: Node,
+        index: Node,
+        value: Node,
+    ) -> Result<Object, EvalError> {
+        let obj = self.eval(object)?;
+        let idx_obj = self.eval(index)?;
+        let val = self.eval(value)?;
+        self.index_set(obj, idx_obj, val)
+    }
+
+    fn index_set(&self, obj: Object, idx_obj: Object, val: Object) -> Result<Object, EvalError> {
+        match obj {
+            Object::List(items) => {
+                let mut items = items.borrow_mut();
+                let pos = self.resolve_index(&idx_obj, items.len())?;
+                items[pos] = val.clone();
+            }
+            Object::Map(entries) => {
+                let key = self.map_key(idx_obj)?;
+                entries.borrow_mut().insert(key, val.clone());
+            }
+            Object::Tuple(_) | Object::SString(_) => return Err(EvalError::NotAssignable(obj)),
+            _ => return Err(EvalError::NotIndexable(obj)),
+        }
+        Ok(val)
+    }
+
+    // negative indexes count from the end: t[-1] is the last item
+    fn resolve_index(&self, idx_obj: &Object, len: usize) -> Result<usize, EvalError> {
+        let idx = match idx_obj {
+            Object::Integer(v) => *v,
+            _ => return Err(EvalError::InvalidIndex(idx_obj.clone())),
+        };
+        let pos = if idx < 0 { idx + len as i64 } else { idx };
+        if pos < 0 || pos >= len as i64 {
+            return Err(EvalError::IndexOutOfRange(idx, len));
+        }
+        Ok(pos as usize)
+    }
+
     fn eval_identifier(&mut self, tk: Token) -> Result<Object, EvalError> {
         match tk {
             Token::Ident(ident) => {
                 if let Some(v) = self.get_value(ident.clone()) {
-                    return Ok(v.clone());
+                    Ok(v)
                 } else {
-                    return Err(EvalError::IdentNotFound(ident));
+                    Err(self.ident_not_found(ident))
                 }
             }
             _ => Err(EvalError::NotIdent(tk)),
         }
     }
 
+    // look for the typo while the scope the name was used in is current
+    fn ident_not_found(&self, ident: String) -> EvalError {
+        let names = self.env.borrow().names();
+        let similar = diagnostic::similar_name(&ident, &names);
+        EvalError::IdentNotFound(ident, similar)
+    }
+
     fn eval_assign(&mut self, name: Token, value: Node) -> Result<Object, EvalError> {
+        let val = self.eval(value)?;
+        self.assign_value(name, val)
+    }
+
+    fn assign_value(&mut self, name: Token, val: Object) -> Result<Object, EvalError> {
         match name {
             Token::Ident(ident) => {
-                let val = self.eval(value)?;
-                self.set_value(ident, val.clone());
+                // only a declared name can be assigned, like with +=, so a typo is
+                // not quietly declared as a new name
+                let assigned = self.env.borrow_mut().assign(&ident, val.clone());
+                if !assigned {
+                    return Err(self.ident_not_found(ident));
+                }
                 Ok(val)
             }
             _ => Err(EvalError::TkIsNotIdent(name)),
         }
     }
 
+    // the target's object and index are evaluated once, then read, combined with
+    // the value and written back
+    fn eval_compound_assign(
+        &mut self,
+        target: Node,
+        tk: Token,
+        value: Node,
+    ) -> Result<Object, EvalError> {
+        let op = match tk {
+            Token::PlusAssign => Token::Plus('+'),
+            Token::MinusAssign => Token::Minus('-'),
+            Token::StarAssign => Token::Star('*'),
+            Token::SlashAssign => Token::Slash('/'),
+            Token::PercentAssign => Token::Percent('%'),
+            _ => return Err(EvalError::NotSupportedOperator(tk)),
+        };
+
+        match target.unspanned().clone() {
+            Node::Identifier(name) => {
+                let current = self.eval_identifier(name.clone())?;
+                let val = self.eval(value)?;
+                let res = self.apply_binary(current, op, val)?;
+                self.assign_value(name, res)
+            }
+            Node::Get(get) => {
+                let obj = self.eval((*get.object).clone())?;
+                let current = self.get_property(obj.clone(), get.name.clone())?;
+                let val = self.eval(value)?;
+                let res = self.apply_binary(current, op, val)?;
+                self.set_property(obj, get.name, res)
+            }
+            Node::Index(index) => {
+                let obj = self.eval((*index.value).clone())?;
+                let idx_obj = self.eval((*index.index).clone())?;
+                let current = self.index_get(obj.clone(), idx_obj.clone())?;
+                let val = self.eval(value)?;
+                let res = self.apply_binary(current, op, val)?;
+                self.index_set(obj, idx_obj, res)
+            }
+            _ => Err(EvalError::UnknowNode(target)),
+        }
+    }
+
+    // && and || short-circuit and give back the operand that decided the result
+    fn eval_logical(&mut self, left: Node, tk: Token, right: Node) -> Result<Object, EvalError> {
+        let left_obj = self.eval(left)?;
+        match tk {
+            Token::And => {
+                if !left_obj.is_truthy() {
+                    return Ok(left_obj);
+                }
+                self.eval(right)
+            }
+            Token::Or => {
+                if left_obj.is_truthy() {
+                    return Ok(left_obj);
+                }
+                self.eval(right)
+            }
+            _ => Err(EvalError::NotSupportedOperator(tk)),
+        }
+    }
+
     fn eval_binary(&mut self, left: Node, tk: Token, right: Node) -> Result<Object, EvalError> {
         let left_obj = self.eval(left)?;
         let right_obj = self.eval(right)?;
+        self.apply_binary(left_obj, tk, right_obj)
+    }
 
+    fn apply_binary(
+        &self,
+        left_obj: Object,
+        tk: Token,
+        right_obj: Object,
+    ) -> Result<Object, EvalError> {
         match tk {
-            Token::Plus(_) | Token::Minus(_) | Token::Slash(_) | Token::Star(_) => {
+            Token::Plus(_)
+            | Token::Minus(_)
+            | Token::Slash(_)
+            | Token::TildeSlash
+            | Token::Percent(_)
+            | Token::Star(_)
+            | Token::DoubleStar => {
+                if matches!(left_obj, Object::SString(_)) || matches!(right_obj, Object::SString(_))
+                {
+                    return self.eval_string_binary(left_obj, tk, right_obj);
+                }
                 self.eval_number(left_obj, tk, right_obj)
             }
+            Token::BitOr | Token::BitAnd | Token::BitXor | Token::ShiftLeft | Token::ShiftRight => {
+                self.eval_bitwise(left_obj, tk, right_obj)
+            }
             Token::Lt(_)
             | Token::LtEQ(_)
             | Token::Gt(_)
             | Token::GtEQ(_)
             | Token::EQ(_)
             | Token::NotEQ(_) => self.eval_compare(left_obj, tk, right_obj),
+            Token::DotDot => self.eval_range(left_obj, right_obj),
             _ => Err(EvalError::NotSupportedOperator(tk)),
         }
     }
 
+    // "a" + x formats x the way print does, so "n=" + 5 is "n=5". "ab" * 3 repeats the
+    // string, a negative count gives "" and a result over MAX_REPEAT_LEN is an error. Any
+    // other operator on a string is an error.
+    fn eval_string_binary(
+        &self,
+        left: Object,
+        tk: Token,
+        right: Object,
+    ) -> Result<Object, EvalError> {
+        match (&tk, &left, &right) {
+            (Token::Plus(_), _, _) => Ok(Object::SString(format!("{}{}", left, right))),
+            (Token::Star(_), Object::SString(s), Object::Integer(n))
+            | (Token::Star(_), Object::Integer(n), Object::SString(s)) => {
+                let count = (*n).max(0) as usize;
+                match s.len().checked_mul(count) {
+                    Some(len) if len <= MAX_REPEAT_LEN => Ok(Object::SString(s.repeat(count))),
+                    _ => Err(EvalError::OperandOutOfRange(tk, Object::Integer(*n))),
+                }
+            }
+            _ => Err(EvalError::InvalidOperands(tk, left, right)),
+        }
+    }
+
+    fn eval_range(&self, left: Object, right: Object) -> Result<Object, EvalError> {
+        match (&left, &right) {
+            (Object::Integer(start), Object::Integer(end)) => Ok(Object::Range(*start, *end)),
+            (Object::Integer(_), _) => Err(EvalError::NotNumber(right)),
+            _ => Err(EvalError::NotNumber(left)),
+        }
+    }
+
     fn eval_compare(&self, left: Object, tk: Token, right: Object) -> Result<Object, EvalError> {
+        let is_ordered = |obj: &Object| {
+            matches!(
+                obj,
+                Object::Integer(_) | Object::BigInt(_) | Object::Float(_) | Object::SString(_)
+            )
+        };
+        if !is_ordered(&left) || !is_ordered(&right) {
+            return self.eval_equality(left, tk, right);
+        }
+
+        // a string is never equal to a number, only ordering them is an error
+        let is_str = |obj: &Object| matches!(obj, Object::SString(_));
+        if is_str(&left) != is_str(&right) && matches!(tk, Token::EQ(_) | Token::NotEQ(_)) {
+            return self.eval_equality(left, tk, right);
+        }
+
+        // integers compare exactly, going through f64 loses precision past 2^53
+        if let (Object::Integer(l), Object::Integer(r)) = (&left, &right) {
+            return self.eval_compare_ordering(tk, l.cmp(r));
+        }
+        if let (Some(l), Some(r)) = (left.to_bigint(), right.to_bigint()) {
+            return self.eval_compare_ordering(tk, l.cmp(&r));
+        }
+
         let mut left_num: f64 = 0.0;
         let mut is_num: bool = false;
         let mut is_str: bool = false;
@@ -193,6 +733,10 @@ impl Interpreter {
                 left_num = v;
                 is_num = true;
             }
+            Object::BigInt(_) => {
+                left_num = left.to_f64().unwrap_or(f64::NAN);
+                is_num = true;
+            }
             Object::SString(ref v) => {
                 left_str = v.clone();
                 is_str = true;
@@ -200,34 +744,77 @@ impl Interpreter {
             _ => return Err(EvalError::NotNumberOrStr(left)),
         };
 
-        let mut right_num: f64 = 0.0;
-        let mut right_str = "".to_string();
         match right {
             Object::Integer(v) => {
-                right_num = v as f64;
                 if !is_num {
                     return Err(EvalError::DifferObjectToCompare(left, right));
                 }
 
-                self.eval_compare_num(tk, left_num, right_num)
+                self.eval_compare_num(tk, left_num, v as f64)
             }
             Object::Float(v) => {
-                right_num = v;
                 if !is_num {
                     return Err(EvalError::DifferObjectToCompare(left, right));
                 }
 
-                self.eval_compare_num(tk, left_num, right_num)
+                self.eval_compare_num(tk, left_num, v)
+            }
+            Object::BigInt(_) => {
+                if !is_num {
+                    return Err(EvalError::DifferObjectToCompare(left, right));
+                }
+
+                self.eval_compare_num(tk, left_num, right.to_f64().unwrap_or(f64::NAN))
             }
             Object::SString(ref v) => {
-                right_str = v.clone();
                 if !is_str {
                     return Err(EvalError::DifferObjectToCompare(left, right));
                 }
 
-                self.eval_compare_str(tk, left_str, right_str)
+                self.eval_compare_str(tk, left_str, v.clone())
+            }
+            _ => Err(EvalError::NotNumberOrStr(right)),
+        }
+    }
+
+    // values without an ordering (tuples, bools, null, ...) only support == and !=
+    fn eval_equality(&self, left: Object, tk: Token, right: Object) -> Result<Object, EvalError> {
+        match tk {
+            Token::EQ(_) => Ok(Object::Bool(self.values_equal(&left, &right))),
+            Token::NotEQ(_) => Ok(Object::Bool(!self.values_equal(&left, &right))),
+            _ => match left {
+                Object::Integer(_) | Object::BigInt(_) | Object::Float(_) | Object::SString(_) => {
+                    Err(EvalError::NotNumberOrStr(right))
+                }
+                _ => Err(EvalError::NotNumberOrStr(left)),
+            },
+        }
+    }
+
+    // == on collections compares their items the way == compares them on their own, so
+    // [1] == [1.0] like 1 == 1.0. integers compare exactly, against a float as f64s
+    fn values_equal(&self, left: &Object, right: &Object) -> bool {
+        let all_equal = |l: &[Object], r: &[Object]| {
+            l.len() == r.len() && l.iter().zip(r).all(|(a, b)| self.values_equal(a, b))
+        };
+        match (left, right) {
+            (Object::Tuple(l), Object::Tuple(r)) => all_equal(l, r),
+            (Object::List(l), Object::List(r)) => all_equal(&l.borrow(), &r.borrow()),
+            (Object::Map(l), Object::Map(r)) => {
+                let (l, r) = (l.borrow(), r.borrow());
+                l.len() == r.len()
+                    && l.iter()
+                        .zip(r.iter())
+                        .all(|((lk, lv), (rk, rv))| lk == rk && self.values_equal(lv, rv))
             }
-            _ => return Err(EvalError::NotNumberOrStr(right)),
+            (Object::Integer(_) | Object::BigInt(_), Object::Integer(_) | Object::BigInt(_)) => {
+                left == right
+            }
+            (
+                Object::Integer(_) | Object::BigInt(_) | Object::Float(_),
+                Object::Integer(_) | Object::BigInt(_) | Object::Float(_),
+            ) => left.to_f64() == right.to_f64(),
+            _ => left == right,
         }
     }
 
@@ -243,6 +830,18 @@ impl Interpreter {
         }
     }
 
+    fn eval_compare_ordering(&self, tk: Token, ord: Ordering) -> Result<Object, EvalError> {
+        match tk {
+            Token::Lt(_) => Ok(Object::Bool(ord == Ordering::Less)),
+            Token::LtEQ(_) => Ok(Object::Bool(ord != Ordering::Greater)),
+            Token::Gt(_) => Ok(Object::Bool(ord == Ordering::Greater)),
+            Token::GtEQ(_) => Ok(Object::Bool(ord != Ordering::Less)),
+            Token::EQ(_) => Ok(Object::Bool(ord == Ordering::Equal)),
+            Token::NotEQ(_) => Ok(Object::Bool(ord != Ordering::Equal)),
+            _ => Err(EvalError::NotSupportedOperator(tk)),
+        }
+    }
+
     fn eval_compare_str(
         &self,
         tk: Token,
@@ -262,80 +861,560 @@ impl Interpreter {
 
     fn eval_number(&self, left: Object, tk: Token, right: Object) -> Result<Object, EvalError> {
         match tk {
-            Token::Plus(_) | Token::Minus(_) | Token::Slash(_) | Token::Star(_) => {
-                self.eval_num_binary(tk, left, right)
-            }
+            Token::Plus(_)
+            | Token::Minus(_)
+            | Token::Slash(_)
+            | Token::TildeSlash
+            | Token::Percent(_)
+            | Token::Star(_)
+            | Token::DoubleStar => self.eval_num_binary(tk, left, right),
             _ => Err(EvalError::NotSupportedOperator(tk)),
         }
     }
 
+    // Integer op Integer stays an Integer and promotes to a BigInt on overflow, except `/`
+    // which is true division and always gives a Float. `~/` is floor division: it rounds
+    // toward negative infinity and keeps integers as integers, `%` is its remainder and
+    // takes the sign of the divisor. `**` with a negative exponent gives a Float. As soon
+    // as one side is a Float the other side is converted to f64 and the result is a Float.
     fn eval_num_binary(&self, tk: Token, left: Object, right: Object) -> Result<Object, EvalError> {
-        let mut left_num: f64 = 0.0;
-        let mut right_num: f64 = 0.0;
-        match left {
-            Object::Integer(v) => left_num = v as f64,
-            Object::Number(v) => left_num = v,
-            Object::Float(v) => left_num = v,
-            _ => return Err(EvalError::NotNumber(left)),
+        if let (Object::Integer(l), Object::Integer(r)) = (&left, &right) {
+            return self.eval_int_binary(tk, *l, *r);
+        }
+        if let (Some(l), Some(r)) = (left.to_bigint(), right.to_bigint()) {
+            return self.eval_big_binary(tk, l, r);
         }
 
-        match right {
-            Object::Integer(v) => right_num = v as f64,
-            Object::Number(v) => right_num = v,
-            Object::Float(v) => right_num = v,
-            _ => return Err(EvalError::NotNumber(right)),
+        let left_num: f64 = match left.to_f64() {
+            Some(v) => v,
+            None => return Err(EvalError::NotNumber(left)),
+        };
+
+        let right_num: f64 = match right.to_f64() {
+            Some(v) => v,
+            None => return Err(EvalError::NotNumber(right)),
+        };
+
+        let is_division = matches!(tk, Token::Slash(_) | Token::TildeSlash | Token::Percent(_));
+        if is_division && right_num == 0.0 {
+            return Err(EvalError::DivideByZero(format!(
+                "right: {:?} num is zero",
+                right
+            )));
         }
 
         match tk {
-            Token::Plus(_) => Ok(Object::Number(left_num + right_num)),
-            Token::Minus(_) => Ok(Object::Number(left_num - right_num)),
-            Token::Star(_) => Ok(Object::Number(left_num * right_num)),
-            Token::Slash(_) => {
-                if right_num == 0.0 {
-                    return Err(EvalError::DivideByZero(format!(
-                        "right: {:?} num is zero",
-                        right
-                    )));
+            Token::Plus(_) => Ok(Object::Float(left_num + right_num)),
+            Token::Minus(_) => Ok(Object::Float(left_num - right_num)),
+            Token::Star(_) => Ok(Object::Float(left_num * right_num)),
+            Token::Slash(_) => Ok(Object::Float(left_num / right_num)),
+            Token::TildeSlash => Ok(Object::Float((left_num / right_num).floor())),
+            Token::Percent(_) => {
+                let rem = left_num % right_num;
+                if rem != 0.0 && (rem < 0.0) != (right_num < 0.0) {
+                    return Ok(Object::Float(rem + right_num));
                 }
-                Ok(Object::Number(left_num / right_num))
+                Ok(Object::Float(rem))
             }
+            Token::DoubleStar => Ok(Object::Float(left_num.powf(right_num))),
             _ => Err(EvalError::NotSupportedOperator(tk)),
         }
     }
 
-    fn eval_return(&mut self, ret_value: Node) -> Result<Object, EvalError> {
-        let obj = self.eval(ret_value)?;
-        Ok(Object::Return(Rc::new(obj)))
+    fn eval_int_binary(&self, tk: Token, left: i64, right: i64) -> Result<Object, EvalError> {
+        let is_division = matches!(tk, Token::Slash(_) | Token::TildeSlash | Token::Percent(_));
+        if is_division && right == 0 {
+            return Err(EvalError::DivideByZero(format!(
+                "right: {:?} num is zero",
+                right
+            )));
+        }
+
+        let res = match tk {
+            Token::Plus(_) => left.checked_add(right),
+            Token::Minus(_) => left.checked_sub(right),
+            Token::Star(_) => left.checked_mul(right),
+            Token::Slash(_) => return Ok(Object::Float(left as f64 / right as f64)),
+            // i64 division truncates toward zero, step down once more when the signs differ
+            Token::TildeSlash => left.checked_div(right).map(|q| {
+                if left % right != 0 && (left < 0) != (right < 0) {
+                    q - 1
+                } else {
+                    q
+                }
+            }),
+            Token::Percent(_) => left.checked_rem(right).map(|r| {
+                if r != 0 && (r < 0) != (right < 0) {
+                    r + right
+                } else {
+                    r
+                }
+            }),
+            Token::DoubleStar => {
+                if right < 0 {
+                    return Ok(Object::Float((left as f64).powf(right as f64)));
+                }
+                u32::try_from(right)
+                    .ok()
+                    .and_then(|exp| left.checked_pow(exp))
+            }
+            _ => return Err(EvalError::NotSupportedOperator(tk)),
+        };
+
+        match res {
+            Some(v) => Ok(Object::Integer(v)),
+            None => self.eval_big_binary(tk, BigInt::from(left), BigInt::from(right)),
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::{
-        ast::{self, Node},
-        errors::EvalError,
-        eval::Interpreter,
-        object::Object,
-        tokens::Token,
-    };
-    use std::rc::Rc;
+    fn eval_big_binary(&self, tk: Token, left: BigInt, right: BigInt) -> Result<Object, EvalError> {
+        let is_division = matches!(tk, Token::Slash(_) | Token::TildeSlash | Token::Percent(_));
+        if is_division && right.is_zero() {
+            return Err(EvalError::DivideByZero(format!(
+                "right: {:?} num is zero",
+                right
+            )));
+        }
 
-    #[test]
-    fn test_eval_integer() {
-        let n = Node::Literal(Token::Integer(100));
-        let mut intpter = Interpreter::new();
-        let v = intpter.eval(n);
-        assert_eq!(v.is_ok(), true);
-        assert_eq!(Object::Integer(100), v.unwrap());
+        let res = match tk {
+            Token::Plus(_) => left + right,
+            Token::Minus(_) => left - right,
+            Token::Star(_) => left * right,
+            Token::Slash(_) => {
+                let (l, r) = (left.to_f64(), right.to_f64());
+                return Ok(Object::Float(l.unwrap_or(f64::NAN) / r.unwrap_or(f64::NAN)));
+            }
+            // BigInt division truncates toward zero as well
+            Token::TildeSlash => {
+                let q = &left / &right;
+                if !(&left % &right).is_zero() && left.is_negative() != right.is_negative() {
+                    q - 1
+                } else {
+                    q
+                }
+            }
+            Token::Percent(_) => {
+                let r = &left % &right;
+                if !r.is_zero() && r.is_negative() != right.is_negative() {
+                    r + right
+                } else {
+                    r
+                }
+            }
+            Token::DoubleStar => {
+                if right.is_negative() {
+                    let (l, r) = (left.to_f64(), right.to_f64());
+                    return Ok(Object::Float(
+                        l.unwrap_or(f64::NAN).powf(r.unwrap_or(f64::NAN)),
+                    ));
+                }
+                // the result has at most exp * left.bits() bits, 0 and 1 stay small
+                match right.to_u32() {
+                    Some(exp) if left.bits() <= 1 || exp as u64 * left.bits() <= MAX_INT_BITS => {
+                        left.pow(exp)
+                    }
+                    _ => return Err(EvalError::OperandOutOfRange(tk, Object::from_bigint(right))),
+                }
+            }
+            _ => return Err(EvalError::NotSupportedOperator(tk)),
+        };
+
+        Ok(Object::from_bigint(res))
     }
 
-    #[test]
-    fn test_eval_literal1() {
-        let n = Node::Literal(Token::SString("Hello, World!".to_string()));
-        let mut intpter = Interpreter::new();
-        let v = intpter.eval(n);
-        assert_eq!(v.is_ok(), true);
-        assert_eq!(Object::SString("Hello, World!".to_string()), v.unwrap());
+    // bitwise operators and shifts only work on integers, BigInts behave as if they
+    // were stored in two's complement with infinite sign extension
+    fn eval_bitwise(&self, left: Object, tk: Token, right: Object) -> Result<Object, EvalError> {
+        if let (Object::Integer(l), Object::Integer(r)) = (&left, &right) {
+            return self.eval_int_bitwise(tk, *l, *r);
+        }
+
+        match (left.to_bigint(), right.to_bigint()) {
+            (Some(l), Some(r)) => self.eval_big_bitwise(tk, l, r),
+            (None, _) => Err(EvalError::NotInteger(tk, left)),
+            (_, None) => Err(EvalError::NotInteger(tk, right)),
+        }
+    }
+
+    fn eval_int_bitwise(&self, tk: Token, left: i64, right: i64) -> Result<Object, EvalError> {
+        match tk {
+            Token::BitAnd => Ok(Object::Integer(left & right)),
+            Token::BitOr => Ok(Object::Integer(left | right)),
+            Token::BitXor => Ok(Object::Integer(left ^ right)),
+            Token::ShiftLeft | Token::ShiftRight => {
+                let shift = match u32::try_from(right) {
+                    Ok(v) => v,
+                    Err(_) => return self.eval_big_bitwise(tk, left.into(), right.into()),
+                };
+                if let Token::ShiftRight = tk {
+                    return Ok(Object::Integer(left >> shift.min(63)));
+                }
+                // bits shifted out of the i64 promote to a BigInt like any other overflow
+                match left.checked_shl(shift) {
+                    Some(v) if v >> shift == left => Ok(Object::Integer(v)),
+                    _ => self.eval_big_bitwise(tk, left.into(), right.into()),
+                }
+            }
+            _ => Err(EvalError::NotSupportedOperator(tk)),
+        }
+    }
+
+    fn eval_big_bitwise(
+        &self,
+        tk: Token,
+        left: BigInt,
+        right: BigInt,
+    ) -> Result<Object, EvalError> {
+        let res = match tk {
+            Token::BitAnd => left & right,
+            Token::BitOr => left | right,
+            Token::BitXor => left ^ right,
+            Token::ShiftLeft | Token::ShiftRight => {
+                let shift = match right.to_u32() {
+                    Some(v) => v,
+                    None => {
+                        return Err(EvalError::OperandOutOfRange(tk, Object::from_bigint(right)))
+                    }
+                };
+                if let Token::ShiftLeft = tk {
+                    if !left.is_zero() && left.bits() + shift as u64 > MAX_INT_BITS {
+                        return Err(EvalError::OperandOutOfRange(tk, Object::from_bigint(right)));
+                    }
+                    left << shift
+                } else {
+                    left >> shift
+                }
+            }
+            _ => return Err(EvalError::NotSupportedOperator(tk)),
+        };
+
+        Ok(Object::from_bigint(res))
+    }
+
+    fn eval_func_decl(
+        &mut self,
+        name: Token,
+        params: Vec<Token>,
+        body: Rc<Node>,
+    ) -> Result<Object, EvalError> {
+        let ident = match name {
+            Token::Ident(ident) => ident,
+            _ => return Err(EvalError::NotIdent(name)),
+        };
+
+        let param_names = self.param_names(params)?;
+        let func = Object::Function(Rc::new(Function::new(
+            ident.clone(),
+            param_names,
+            body,
+            self.env.clone(),
+        )));
+        self.set_value(ident, func.clone());
+        Ok(func)
+    }
+
+    fn eval_lambda(&mut self, params: Vec<Token>, body: Rc<Node>) -> Result<Object, EvalError> {
+        let param_names = self.param_names(params)?;
+        Ok(Object::Function(Rc::new(Function::new(
+            "lambda".to_string(),
+            param_names,
+            body,
+            self.env.clone(),
+        ))))
+    }
+
+    fn param_names(&self, params: Vec<Token>) -> Result<Vec<String>, EvalError> {
+        let mut param_names: Vec<String> = vec![];
+        for param in params {
+            match param {
+                Token::Ident(p) => param_names.push(p),
+                _ => return Err(EvalError::NotIdent(param)),
+            }
+        }
+        Ok(param_names)
+    }
+
+    fn eval_call(&mut self, callee: Node, args: Vec<Node>) -> Result<Object, EvalError> {
+        let callee_name = match callee.unspanned() {
+            Node::Identifier(Token::Ident(ident)) => Some(ident.clone()),
+            _ => None,
+        };
+
+        let callee_obj = self.eval(callee)?;
+        let mut arg_objs: Vec<Object> = vec![];
+        for arg in args {
+            arg_objs.push(self.eval(arg)?);
+        }
+
+        match callee_obj {
+            Object::Function(func) => self.call_function(func, arg_objs),
+            Object::Class(class) => self.instantiate(class, arg_objs),
+            _ => Err(EvalError::IdentifierIsNotCallable(
+                callee_name.unwrap_or(callee_obj.to_string()),
+            )),
+        }
+    }
+
+    fn eval_class_decl(
+        &mut self,
+        name: Token,
+        superclass: Node,
+        methods: Vec<ast::FuncDecl>,
+    ) -> Result<Object, EvalError> {
+        let ident = match name {
+            Token::Ident(ident) => ident,
+            _ => return Err(EvalError::NotIdent(name)),
+        };
+
+        let parent = match superclass.unspanned() {
+            Node::Null => None,
+            node => {
+                let parent_name = match node {
+                    Node::Identifier(Token::Ident(v)) => v.clone(),
+                    _ => String::new(),
+                };
+                // point at the superclass name, not the whole class
+                let not_class = EvalError::InheritFromNonClass(parent_name);
+                let not_class = match &superclass {
+                    Node::Spanned(span, _) => not_class.at(span),
+                    _ => not_class,
+                };
+                match self.eval(superclass.clone())? {
+                    Object::Class(class) => Some(class),
+                    _ => return Err(not_class),
+                }
+            }
+        };
+
+        // methods see `super` through one extra scope between them and the class declaration
+        let mut method_env = self.new_scope();
+        match parent {
+            Some(ref class) => method_env.define("super".to_string(), Object::Class(class.clone())),
+            None => method_env.define("super".to_string(), Object::Null),
+        }
+        let method_env = Rc::new(RefCell::new(method_env));
+
+        let mut method_map: HashMap<String, Rc<Function>> = HashMap::new();
+        for method in methods {
+            let method_name = match method.name {
+                Token::Ident(ref v) => v.clone(),
+                _ => return Err(EvalError::NotIdent(method.name)),
+            };
+            let params = self.param_names(method.params)?;
+            let func = Function::new(method_name.clone(), params, method.body, method_env.clone());
+            method_map.insert(method_name, Rc::new(func));
+        }
+
+        let class = Object::Class(Rc::new(Class::new(ident.clone(), parent, method_map)));
+        self.set_value(ident, class.clone());
+        Ok(class)
+    }
+
+    // calling a class makes an instance and runs its init method
+    fn instantiate(&mut self, class: Rc<Class>, args: Vec<Object>) -> Result<Object, EvalError> {
+        let instance = Object::ClassInstance(Rc::new(Instance::new(class.clone())));
+        match class.find_method("init") {
+            Some(init) => {
+                self.call_function(Rc::new(init.bind(instance.clone())), args)?;
+            }
+            None => {
+                if !args.is_empty() {
+                    return Err(EvalError::ArgumentsMismatch(
+                        class.name.clone(),
+                        0,
+                        args.len(),
+                    ));
+                }
+            }
+        }
+        Ok(instance)
+    }
+
+    fn eval_get(&mut self, object: Node, name: Token) -> Result<Object, EvalError> {
+        let obj = self.eval(object)?;
+        self.get_property(obj, name)
+    }
+
+    fn get_property(&self, obj: Object, name: Token) -> Result<Object, EvalError> {
+        let ident = match name {
+            Token::Ident(ident) => ident,
+            _ => return Err(EvalError::NotIdent(name)),
+        };
+
+        let instance = match obj {
+            Object::ClassInstance(ref instance) => instance.clone(),
+            _ => return Err(EvalError::OnlyClassInstanceHaveProperty(obj.to_string())),
+        };
+
+        // fields shadow methods
+        if let Some(v) = instance.fields.borrow().get(&ident) {
+            return Ok(v.clone());
+        }
+
+        match instance.class.find_method(&ident) {
+            Some(method) => Ok(Object::Function(Rc::new(method.bind(obj)))),
+            None => Err(EvalError::UndefinedProperty(ident)),
+        }
+    }
+
+    // super.method looks the method up from the parent class and binds it to this
+    fn eval_super(&mut self, method: Token) -> Result<Object, EvalError> {
+        let ident = match method {
+            Token::Ident(ident) => ident,
+            _ => return Err(EvalError::NotIdent(method)),
+        };
+
+        let this = self.get_value("this".to_string());
+        let superclass = self.get_value("super".to_string());
+        let (this, superclass) = match (this, superclass) {
+            (Some(this), Some(superclass)) => (this, superclass),
+            _ => return Err(EvalError::SuperOutsideMethod),
+        };
+
+        match superclass {
+            Object::Class(class) => match class.find_method(&ident) {
+                Some(func) => Ok(Object::Function(Rc::new(func.bind(this)))),
+                None => Err(EvalError::UndefinedProperty(ident)),
+            },
+            _ => match this {
+                Object::ClassInstance(instance) => {
+                    Err(EvalError::NoSuperclass(instance.class.name.clone()))
+                }
+                _ => Err(EvalError::SuperOutsideMethod),
+            },
+        }
+    }
+
+    fn eval_set(&mut self, object: Node, name: Token, value: Node) -> Result<Object, EvalError> {
+        let obj = self.eval(object)?;
+        if !matches!(obj, Object::ClassInstance(_)) {
+            return Err(EvalError::OnlyClassInstanceHaveProperty(obj.to_string()));
+        }
+
+        let val = self.eval(value)?;
+        self.set_property(obj, name, val)
+    }
+
+    fn set_property(&self, obj: Object, name: Token, val: Object) -> Result<Object, EvalError> {
+        let ident = match name {
+            Token::Ident(ident) => ident,
+            _ => return Err(EvalError::NotIdent(name)),
+        };
+
+        let instance = match obj {
+            Object::ClassInstance(instance) => instance,
+            _ => return Err(EvalError::OnlyClassInstanceHaveProperty(obj.to_string())),
+        };
+
+        instance.fields.borrow_mut().insert(ident, val.clone());
+        Ok(val)
+    }
+
+    fn call_function(
+        &mut self,
+        func: Rc<Function>,
+        args: Vec<Object>,
+    ) -> Result<Object, EvalError> {
+        if func.params.len() != args.len() {
+            return Err(EvalError::ArgumentsMismatch(
+                func.name.clone(),
+                func.params.len(),
+                args.len(),
+            ));
+        }
+
+        let mut scope = Environment::new_enclosed(func.env.clone());
+        for (param, arg) in func.params.iter().zip(args) {
+            scope.define(param.clone(), arg);
+        }
+
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(EvalError::RecursionLimit(MAX_CALL_DEPTH));
+        }
+        let body = (*func.body).clone();
+        self.depth += 1;
+        let result = self.eval_in_scope(scope, |intpter| intpter.eval(body));
+        self.depth -= 1;
+        match result? {
+            Object::Return(val) => Ok((*val).clone()),
+            Object::Break => Err(EvalError::OutsideLoop(Token::Break)),
+            Object::Continue => Err(EvalError::OutsideLoop(Token::Continue)),
+            _ => Ok(Object::Null),
+        }
+    }
+
+    // print(a, b) writes the values separated by a space and ends the line
+    fn eval_print(&mut self, args: Vec<Node>) -> Result<Object, EvalError> {
+        let mut values: Vec<Object> = vec![];
+        for arg in args {
+            values.push(self.eval(arg)?);
+        }
+
+        let line = Object::Print(values);
+        writeln!(self.out, "{}", line).map_err(|e| EvalError::WriteOutput(e.to_string()))?;
+        self.out
+            .flush()
+            .map_err(|e| EvalError::WriteOutput(e.to_string()))?;
+        Ok(Object::Null)
+    }
+
+    fn eval_return(&mut self, ret_value: Node) -> Result<Object, EvalError> {
+        let obj = self.eval(ret_value)?;
+        Ok(Object::Return(Rc::new(obj)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigInt;
+
+    use crate::{
+        ast::{self, Node},
+        errors::EvalError,
+        eval::{Interpreter, MAX_CALL_DEPTH},
+        lexer::Lexer,
+        object::Object,
+        parser::Parser,
+        tokens::Token,
+    };
+    use std::{cell::RefCell, io::Write, rc::Rc};
+
+    // a print sink the test can read back after the interpreter is done with it
+    #[derive(Clone)]
+    struct SharedBuf(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn eval_input(intpter: &mut Interpreter, input: &str) -> Result<Object, EvalError> {
+        let tokens = Lexer::new(input.to_string()).scan_tokens().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        intpter.eval_program(program)
+    }
+
+    #[test]
+    fn test_eval_integer() {
+        let n = Node::Literal(Token::Integer(100));
+        let mut intpter = Interpreter::new();
+        let v = intpter.eval(n);
+        assert!(v.is_ok());
+        assert_eq!(Object::Integer(100), v.unwrap());
+    }
+
+    #[test]
+    fn test_eval_literal1() {
+        let n = Node::Literal(Token::SString("Hello, World!".to_string()));
+        let mut intpter = Interpreter::new();
+        let v = intpter.eval(n);
+        assert!(v.is_ok());
+        assert_eq!(Object::SString("Hello, World!".to_string()), v.unwrap());
     }
 
     #[test]
@@ -347,7 +1426,7 @@ mod tests {
             Node::Literal(Token::Float(1024.8)),
             Node::Literal(Token::SString("Hi".to_string())),
         ];
-        let expect_obj = vec![
+        let expect_obj = [
             Object::Bool(true),
             Object::Bool(false),
             Object::Integer(1024),
@@ -356,12 +1435,10 @@ mod tests {
         ];
         let mut intpter = Interpreter::new();
 
-        let mut idx = 0 as usize;
-        for node in nodes {
+        for (idx, node) in nodes.into_iter().enumerate() {
             let v = intpter.eval(node);
-            assert_eq!(v.is_ok(), true);
+            assert!(v.is_ok());
             assert_eq!(expect_obj[idx], v.unwrap());
-            idx += 1;
         }
     }
 
@@ -372,14 +1449,28 @@ mod tests {
             Node::Literal(Token::Integer(1024)),
         ));
         let mut intpter = Interpreter::new();
+        assert!(eval_input(&mut intpter, "var x = 0").is_ok());
         let v = intpter.eval(n);
-        assert_eq!(v.is_ok(), true);
+        assert!(v.is_ok());
         assert_eq!(Object::Integer(1024), v.unwrap());
 
         let val = intpter.get_value("x".to_string());
         println!("{:?}", val);
-        assert_eq!(val.is_some(), true);
-        assert_eq!(*val.unwrap(), Object::Integer(1024));
+        assert!(val.is_some());
+        assert_eq!(val.unwrap(), Object::Integer(1024));
+
+        // a name has to be declared before it is assigned, in a nested scope too
+        let v = eval_input(&mut intpter, "y = 1");
+        assert_eq!(Err(EvalError::IdentNotFound("y".to_string(), None)), v);
+        let v = eval_input(&mut intpter, "var count = 0\nif (true) { cont = 1 }");
+        assert_eq!(
+            Err(EvalError::IdentNotFound(
+                "cont".to_string(),
+                Some("count".to_string())
+            )),
+            v
+        );
+        assert_eq!(None, intpter.get_value("cont".to_string()));
     }
 
     #[test]
@@ -390,11 +1481,8 @@ mod tests {
         ));
         let mut intpter = Interpreter::new();
         let v = intpter.eval(n);
-        assert_eq!(v.is_err(), true);
-        assert_eq!(
-            v.err(),
-            Some(EvalError::TkIsNotIdent(Token::SString("x".to_string())))
-        );
+        assert!(v.is_err());
+        assert_eq!(v.err(), Some(EvalError::TkIsNotIdent(Token::Integer(1))));
     }
 
     #[test]
@@ -407,8 +1495,8 @@ mod tests {
         let mut intpter = Interpreter::new();
         let v = intpter.eval(n);
         println!("obj: {:?}", v);
-        assert_eq!(v.is_ok(), true);
-        assert_eq!(Object::Number(2048 as f64), v.unwrap());
+        assert!(v.is_ok());
+        assert_eq!(Object::Integer(2048), v.unwrap());
 
         let v1 = intpter.eval(Node::Binary(
             Rc::new(Node::Literal(Token::Integer(1))),
@@ -416,8 +1504,8 @@ mod tests {
             Rc::new(Node::Literal(Token::Integer(100))),
         ));
         println!("obj: {:?}", v1);
-        assert_eq!(v1.is_ok(), true);
-        assert_eq!(Object::Number(-99 as f64), v1.unwrap());
+        assert!(v1.is_ok());
+        assert_eq!(Object::Integer(-99), v1.unwrap());
 
         let v1 = intpter.eval(Node::Binary(
             Rc::new(Node::Literal(Token::Integer(2))),
@@ -425,8 +1513,8 @@ mod tests {
             Rc::new(Node::Literal(Token::Integer(256))),
         ));
         println!("obj: {:?}", v1);
-        assert_eq!(v1.is_ok(), true);
-        assert_eq!(Object::Number(512 as f64), v1.unwrap());
+        assert!(v1.is_ok());
+        assert_eq!(Object::Integer(512), v1.unwrap());
 
         let v1 = intpter.eval(Node::Binary(
             Rc::new(Node::Literal(Token::Integer(2048))),
@@ -434,8 +1522,8 @@ mod tests {
             Rc::new(Node::Literal(Token::Integer(2))),
         ));
         println!("2048 / 2 = : {:?}", v1);
-        assert_eq!(v1.is_ok(), true);
-        assert_eq!(Object::Number(1024 as f64), v1.unwrap());
+        assert!(v1.is_ok());
+        assert_eq!(Object::Float(1024_f64), v1.unwrap());
     }
 
     #[test]
@@ -464,20 +1552,18 @@ mod tests {
         ];
 
         let mut intpter = Interpreter::new();
-        let expect_result = vec![
+        let expect_result = [
             Object::Bool(false),
             Object::Bool(true),
             Object::Bool(false),
             Object::Bool(true),
         ];
 
-        let mut idx = 0;
-        for n in nodes {
+        for (idx, n) in nodes.into_iter().enumerate() {
             let v = intpter.eval(n);
             println!("obj: {:?}", v);
-            assert_eq!(v.is_ok(), true);
+            assert!(v.is_ok());
             assert_eq!(expect_result[idx], v.unwrap());
-            idx += 1;
         }
     }
 
@@ -491,7 +1577,7 @@ mod tests {
         let mut intpter = Interpreter::new();
         let v = intpter.eval(n);
         println!("obj: {:?}", v);
-        assert_eq!(v.is_err(), true);
+        assert!(v.is_err());
         assert_eq!(
             v.err(),
             Some(EvalError::DifferObjectToCompare(
@@ -510,12 +1596,12 @@ mod tests {
         let mut intpter = Interpreter::new();
         let v = intpter.eval(n);
         println!("obj: {:?}", v);
-        assert_eq!(v.is_ok(), true);
+        assert!(v.is_ok());
         assert_eq!(Object::Integer(1024), v.unwrap());
 
         let x_v = intpter.get_value("x".to_string());
         println!("x_v: {:?}", x_v);
-        assert_eq!(Some(&Object::Integer(1024)), x_v);
+        assert_eq!(Some(Object::Integer(1024)), x_v);
     }
 
     #[test]
@@ -527,10 +1613,1267 @@ mod tests {
         let mut intpter = Interpreter::new();
         let v = intpter.eval(n);
         println!("obj: {:?}", v);
-        assert_eq!(v.is_err(), true);
+        assert!(v.is_err());
+        assert_eq!(Some(EvalError::NotIdent(Token::Integer(1))), v.err());
+    }
+
+    #[test]
+    fn test_eval_while() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            "var x = 0 var sum = 0 while (x < 10) { x = x + 1 sum = sum + x }",
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Integer(10)),
+            intpter.get_value("x".to_string())
+        );
+        assert_eq!(
+            Some(Object::Integer(55)),
+            intpter.get_value("sum".to_string())
+        );
+    }
+
+    #[test]
+    fn test_eval_while_break_continue() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            var x = 0
+            var odd = 0
+            while (true) {
+                x = x + 1
+                if (x > 9) {
+                    break
+                }
+                if (x > 5) {
+                    continue
+                }
+                odd = odd + x
+            }
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Integer(10)),
+            intpter.get_value("x".to_string())
+        );
+        assert_eq!(
+            Some(Object::Integer(15)),
+            intpter.get_value("odd".to_string())
+        );
+    }
+
+    #[test]
+    fn test_eval_break_outside_loop() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(&mut intpter, "if (true) { break }");
+        assert_eq!(Some(EvalError::OutsideLoop(Token::Break)), v.err());
+
+        let v = eval_input(&mut intpter, "continue");
+        assert_eq!(Some(EvalError::OutsideLoop(Token::Continue)), v.err());
+    }
+
+    #[test]
+    fn test_eval_for() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            var sum = 0
+            for (var i = 0; i < 10; i = i + 1) {
+                if (i > 4) {
+                    break
+                }
+                sum = sum + i
+            }
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Integer(10)),
+            intpter.get_value("sum".to_string())
+        );
+        assert_eq!(None, intpter.get_value("i".to_string()));
+    }
+
+    #[test]
+    fn test_eval_for_in() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            var x = "outer"
+            var sum = 0
+            for x in 1..5 {
+                if (x == 2) {
+                    continue
+                }
+                sum = sum + x
+            }
+            var s = ""
+            var count = 0
+            for c in "sip" {
+                s = c
+                count = count + 1
+            }
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Integer(8)),
+            intpter.get_value("sum".to_string())
+        );
+        assert_eq!(
+            Some(Object::SString("outer".to_string())),
+            intpter.get_value("x".to_string())
+        );
+        assert_eq!(
+            Some(Object::SString("p".to_string())),
+            intpter.get_value("s".to_string())
+        );
+        assert_eq!(
+            Some(Object::Integer(3)),
+            intpter.get_value("count".to_string())
+        );
+
+        let v = eval_input(&mut intpter, "for x in true {}");
+        assert_eq!(Some(EvalError::NotIterable(Object::Bool(true))), v.err());
+    }
+
+    #[test]
+    fn test_eval_func_call() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            def add(a, b) {
+                return a + b
+            }
+            def fib(n) {
+                if (n < 2) {
+                    return n
+                } else {
+                    return fib(n - 1) + fib(n - 2)
+                }
+            }
+            def noop() {}
+            var x = add(1, 2)
+            var y = fib(10)
+            var z = noop()
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(Some(Object::Integer(3)), intpter.get_value("x".to_string()));
+        assert_eq!(
+            Some(Object::Integer(55)),
+            intpter.get_value("y".to_string())
+        );
+        assert_eq!(Some(Object::Null), intpter.get_value("z".to_string()));
+    }
+
+    #[test]
+    fn test_eval_call_err() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(&mut intpter, "def add(a, b) { return a + b } add(1)");
+        assert_eq!(
+            Some(EvalError::ArgumentsMismatch("add".to_string(), 2, 1)),
+            v.err()
+        );
+
+        let v = eval_input(&mut intpter, "var x = 1 x(2)");
         assert_eq!(
-            Some(EvalError::NotIdent(Token::SString("x".to_string()))),
+            Some(EvalError::IdentifierIsNotCallable("x".to_string())),
             v.err()
         );
     }
+
+    #[test]
+    fn test_eval_block_scope() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            var x = 1
+            var y = 1
+            {
+                var x = 2
+                y = x
+                var z = 3
+            }
+            if (true) {
+                var y = 100
+            }
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(Some(Object::Integer(1)), intpter.get_value("x".to_string()));
+        assert_eq!(Some(Object::Integer(2)), intpter.get_value("y".to_string()));
+        assert_eq!(None, intpter.get_value("z".to_string()));
+    }
+
+    #[test]
+    fn test_eval_func_scope() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            var count = 0
+            var a = "global"
+            def inc(a) {
+                var local = a
+                count = count + a
+            }
+            inc(1)
+            inc(2)
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Integer(3)),
+            intpter.get_value("count".to_string())
+        );
+        assert_eq!(
+            Some(Object::SString("global".to_string())),
+            intpter.get_value("a".to_string())
+        );
+        assert_eq!(None, intpter.get_value("local".to_string()));
+    }
+
+    #[test]
+    fn test_eval_closure() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            def counter() {
+                var count = 0
+                def inc() {
+                    count = count + 1
+                    return count
+                }
+                return inc
+            }
+            var c1 = counter()
+            var c2 = counter()
+            c1()
+            c1()
+            var a = c1()
+            var b = c2()
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(Some(Object::Integer(3)), intpter.get_value("a".to_string()));
+        assert_eq!(Some(Object::Integer(1)), intpter.get_value("b".to_string()));
+        assert_eq!(None, intpter.get_value("count".to_string()));
+    }
+
+    #[test]
+    fn test_eval_closure_shared_capture() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            var add = 0
+            var get = 0
+            def make() {
+                var n = 10
+                def addn(x) {
+                    n = n + x
+                }
+                def getn() {
+                    return n
+                }
+                add = addn
+                get = getn
+            }
+            make()
+            add(5)
+            add(1)
+            var total = get()
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Integer(16)),
+            intpter.get_value("total".to_string())
+        );
+    }
+
+    #[test]
+    fn test_eval_return_early() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            var calls = 0
+            def find(limit) {
+                for x in 0..100 {
+                    while (true) {
+                        if (x == limit) {
+                            return x
+                        }
+                        break
+                    }
+                }
+                calls = calls + 1
+                return -1
+            }
+            def sign(n) {
+                if (n < 0) {
+                    return -1
+                }
+                calls = calls + 1
+                if (n == 0) {
+                    return
+                }
+                return 1
+            }
+            var a = find(7)
+            var b = find(200)
+            var c = sign(-5)
+            var d = sign(0)
+            var e = sign(3)
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(Some(Object::Integer(7)), intpter.get_value("a".to_string()));
+        assert_eq!(
+            Some(Object::Integer(-1)),
+            intpter.get_value("b".to_string())
+        );
+        assert_eq!(
+            Some(Object::Integer(-1)),
+            intpter.get_value("c".to_string())
+        );
+        assert_eq!(Some(Object::Null), intpter.get_value("d".to_string()));
+        assert_eq!(Some(Object::Integer(1)), intpter.get_value("e".to_string()));
+        assert_eq!(
+            Some(Object::Integer(3)),
+            intpter.get_value("calls".to_string())
+        );
+    }
+
+    #[test]
+    fn test_eval_return_top_level() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            var x = 1
+            if (x == 1) {
+                return "done"
+            }
+            x = 2
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert_eq!(Ok(Object::SString("done".to_string())), v);
+        assert_eq!(Some(Object::Integer(1)), intpter.get_value("x".to_string()));
+    }
+
+    #[test]
+    fn test_eval_logical() {
+        let mut intpter = Interpreter::new();
+        let inputs = vec![
+            ("1 < 2 && 2 < 3", Object::Bool(true)),
+            ("1 < 2 && 3 < 2", Object::Bool(false)),
+            ("1 > 2 || 2 < 3", Object::Bool(true)),
+            ("0 || \"default\"", Object::SString("default".to_string())),
+            ("\"\" && 1", Object::SString("".to_string())),
+            ("!0", Object::Bool(true)),
+            ("!\"sip\"", Object::Bool(false)),
+            ("var empty = 0..0 !empty", Object::Bool(true)),
+        ];
+
+        for (input, expect) in inputs {
+            let v = eval_input(&mut intpter, input);
+            println!("{} => {:?}", input, v);
+            assert_eq!(Ok(expect), v);
+        }
+    }
+
+    #[test]
+    fn test_eval_logical_short_circuit() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            var calls = 0
+            def touch() {
+                calls = calls + 1
+                return true
+            }
+            var a = false && touch()
+            var b = true || touch()
+            var c = true && touch()
+            var d = 0
+            if (calls) {
+                d = 1
+            }
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Bool(false)),
+            intpter.get_value("a".to_string())
+        );
+        assert_eq!(Some(Object::Bool(true)), intpter.get_value("b".to_string()));
+        assert_eq!(Some(Object::Bool(true)), intpter.get_value("c".to_string()));
+        assert_eq!(
+            Some(Object::Integer(1)),
+            intpter.get_value("calls".to_string())
+        );
+        assert_eq!(Some(Object::Integer(1)), intpter.get_value("d".to_string()));
+    }
+
+    #[test]
+    fn test_eval_group() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(&mut intpter, "(1 + 2) * 3");
+        println!("obj: {:?}", v);
+        assert_eq!(Ok(Object::Integer(9)), v);
+
+        let v = eval_input(&mut intpter, "!(0..0)");
+        assert_eq!(Ok(Object::Bool(true)), v);
+    }
+
+    #[test]
+    fn test_eval_tuple() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            def divmod(a, b) {
+                return a / b, a - b
+            }
+            var t = (1, "two", (3,))
+            var first = t[0]
+            var last = t[-1]
+            var same = t == (1, "two", (3,))
+            var differ = t != (1, 2)
+            var (q, r) = divmod(9, 3)
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Integer(1)),
+            intpter.get_value("first".to_string())
+        );
+        assert_eq!(
+            Some(Object::Tuple(vec![Object::Integer(3)])),
+            intpter.get_value("last".to_string())
+        );
+        assert_eq!(
+            Some(Object::Bool(true)),
+            intpter.get_value("same".to_string())
+        );
+        assert_eq!(
+            Some(Object::Bool(true)),
+            intpter.get_value("differ".to_string())
+        );
+        assert_eq!(Some(Object::Float(3.0)), intpter.get_value("q".to_string()));
+        assert_eq!(Some(Object::Integer(6)), intpter.get_value("r".to_string()));
+        assert_eq!(
+            "(1, \"two\", (3,))",
+            intpter.get_value("t".to_string()).unwrap().to_string()
+        );
+
+        let v = eval_input(&mut intpter, "t[3]");
+        assert_eq!(Some(EvalError::IndexOutOfRange(3, 3)), v.err());
+
+        let v = eval_input(&mut intpter, "var (a, b) = (1, 2, 3)");
+        assert_eq!(Some(EvalError::DestructureMismatch(2, 3)), v.err());
+    }
+
+    #[test]
+    fn test_eval_lambda() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            def apply(f, a, b) {
+                return f(a, b)
+            }
+            def adder(n) {
+                return x => x + n
+            }
+            var double = (x) => x * 2
+            var less = apply(def (a, b) { return a < b }, 1, 2)
+            var sum = apply((a, b) => { return a + b }, 3, 4)
+            var add5 = adder(5)
+            var a = double(21)
+            var b = add5(1)
+            var c = (() => "now")()
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Bool(true)),
+            intpter.get_value("less".to_string())
+        );
+        assert_eq!(
+            Some(Object::Integer(7)),
+            intpter.get_value("sum".to_string())
+        );
+        assert_eq!(
+            Some(Object::Integer(42)),
+            intpter.get_value("a".to_string())
+        );
+        assert_eq!(Some(Object::Integer(6)), intpter.get_value("b".to_string()));
+        assert_eq!(
+            Some(Object::SString("now".to_string())),
+            intpter.get_value("c".to_string())
+        );
+    }
+
+    #[test]
+    fn test_eval_class() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            class Point {
+                def init(x, y) {
+                    this.x = x
+                    this.y = y
+                }
+                def len2() {
+                    return this.x * this.x + this.y * this.y
+                }
+                def move(dx) {
+                    this.x = this.x + dx
+                    return this
+                }
+            }
+            class Empty {}
+            var p = Point(3, 4)
+            var l = p.len2()
+            var m = p.len2
+            p.move(1).move(1)
+            var x = p.x
+            var l2 = m()
+            var e = Empty()
+            e.tag = "empty"
+            var tag = e.tag
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Integer(25)),
+            intpter.get_value("l".to_string())
+        );
+        assert_eq!(Some(Object::Integer(5)), intpter.get_value("x".to_string()));
+        assert_eq!(
+            Some(Object::Integer(41)),
+            intpter.get_value("l2".to_string())
+        );
+        assert_eq!(
+            Some(Object::SString("empty".to_string())),
+            intpter.get_value("tag".to_string())
+        );
+        assert_eq!(
+            "<Point instance>",
+            intpter.get_value("p".to_string()).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_eval_class_err() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(&mut intpter, "var n = 1 n.x");
+        assert_eq!(
+            Some(EvalError::OnlyClassInstanceHaveProperty("1".to_string())),
+            v.err()
+        );
+
+        let v = eval_input(&mut intpter, "var n = 1 n.x = 2");
+        assert_eq!(
+            Some(EvalError::OnlyClassInstanceHaveProperty("1".to_string())),
+            v.err()
+        );
+
+        let v = eval_input(&mut intpter, "class A {} var a = A() a.missing");
+        assert_eq!(
+            Some(EvalError::UndefinedProperty("missing".to_string())),
+            v.err()
+        );
+
+        let v = eval_input(&mut intpter, "A(1)");
+        assert_eq!(
+            Some(EvalError::ArgumentsMismatch("A".to_string(), 0, 1)),
+            v.err()
+        );
+    }
+
+    #[test]
+    fn test_eval_inheritance() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            class Shape {
+                def init(name) {
+                    this.name = name
+                }
+                def area() {
+                    return 0
+                }
+                def describe() {
+                    return this.area()
+                }
+            }
+            class Square < Shape {
+                def init(side) {
+                    super.init("square")
+                    this.side = side
+                }
+                def area() {
+                    return this.side * this.side
+                }
+            }
+            class Cube < Square {
+                def area() {
+                    return super.area() * 6
+                }
+            }
+            class Named < Shape {}
+            var s = Square(3)
+            var c = Cube(2)
+            var n = Named("blob")
+            var sa = s.describe()
+            var ca = c.describe()
+            var cn = c.name
+            var na = n.area()
+            var nn = n.name
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert!(v.is_ok());
+        assert_eq!(
+            Some(Object::Integer(9)),
+            intpter.get_value("sa".to_string())
+        );
+        assert_eq!(
+            Some(Object::Integer(24)),
+            intpter.get_value("ca".to_string())
+        );
+        assert_eq!(
+            Some(Object::SString("square".to_string())),
+            intpter.get_value("cn".to_string())
+        );
+        assert_eq!(
+            Some(Object::Integer(0)),
+            intpter.get_value("na".to_string())
+        );
+        assert_eq!(
+            Some(Object::SString("blob".to_string())),
+            intpter.get_value("nn".to_string())
+        );
+    }
+
+    #[test]
+    fn test_eval_inheritance_err() {
+        let mut intpter = Interpreter::new();
+        let v = eval_input(&mut intpter, "var NotClass = 1 class A < NotClass {}");
+        assert_eq!(
+            Some(EvalError::InheritFromNonClass("NotClass".to_string())),
+            v.err()
+        );
+
+        let v = eval_input(&mut intpter, "super.init()");
+        assert_eq!(Some(EvalError::SuperOutsideMethod), v.err());
+
+        let v = eval_input(
+            &mut intpter,
+            "class B { def f() { return super.f() } } var b = B() b.f()",
+        );
+        assert_eq!(Some(EvalError::NoSuperclass("B".to_string())), v.err());
+    }
+
+    #[test]
+    fn test_eval_print() {
+        let buf = SharedBuf(Rc::new(RefCell::new(vec![])));
+        let mut intpter = Interpreter::with_output(Box::new(buf.clone()));
+        let v = eval_input(
+            &mut intpter,
+            r#"
+            class Point {}
+            def f() {}
+            print("hello", "sip")
+            print(1, 2.5, 1.0 + 1, true, (1, "a"))
+            print(f(), f, Point, Point(), 0..3)
+            print()
+            "#,
+        );
+        println!("obj: {:?}", v);
+        assert_eq!(Ok(Object::Null), v);
+        assert_eq!(
+            "hello sip\n1 2.5 2.0 true (1, \"a\")\nnull <fn f> <class Point> <Point instance> 0..3\n\n",
+            String::from_utf8(buf.0.borrow().clone()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_eval_integer_arith() {
+        let mut intpter = Interpreter::new();
+        let inputs = vec![
+            ("7 + 8 * 2", Ok(Object::Integer(23))),
+            ("7 / 2", Ok(Object::Float(3.5))),
+            ("7 ~/ 2", Ok(Object::Integer(3))),
+            ("-7 ~/ 2", Ok(Object::Integer(-4))),
+            ("7 ~/ -2", Ok(Object::Integer(-4))),
+            ("-8 ~/ 2", Ok(Object::Integer(-4))),
+            ("7.5 ~/ 2", Ok(Object::Float(3.0))),
+            ("1 + 0.5", Ok(Object::Float(1.5))),
+            ("2 * 1.5", Ok(Object::Float(3.0))),
+            (
+                "9223372036854775807 + 1",
+                Ok(Object::BigInt(
+                    "9223372036854775808".parse::<BigInt>().unwrap(),
+                )),
+            ),
+            (
+                "4611686018427387904 * 2",
+                Ok(Object::BigInt(
+                    "9223372036854775808".parse::<BigInt>().unwrap(),
+                )),
+            ),
+            (
+                "1 ~/ 0",
+                Err(EvalError::DivideByZero("right: 0 num is zero".to_string())),
+            ),
+        ];
+
+        for (input, expect) in inputs {
+            let v = eval_input(&mut intpter, input);
+            println!("{} => {:?}", input, v);
+            assert_eq!(expect, v);
+        }
+    }
+
+    #[test]
+    fn test_eval_bigint() {
+        let big = |s: &str| Object::BigInt(s.parse::<BigInt>().unwrap());
+        let mut intpter = Interpreter::new();
+        let inputs = vec![
+            ("18446744073709551616", Ok(big("18446744073709551616"))),
+            ("-9223372036854775807 - 1", Ok(Object::Integer(i64::MIN))),
+            (
+                "-(-9223372036854775807 - 1)",
+                Ok(big("9223372036854775808")),
+            ),
+            (
+                "4294967296 * 4294967296 * 4294967296",
+                Ok(big("79228162514264337593543950336")),
+            ),
+            // results that fit again come back as plain integers
+            (
+                "18446744073709551616 - 18446744073709551615",
+                Ok(Object::Integer(1)),
+            ),
+            (
+                "18446744073709551616 ~/ 4294967296",
+                Ok(Object::Integer(4294967296)),
+            ),
+            (
+                "-18446744073709551617 ~/ 2",
+                Ok(big("-9223372036854775809")),
+            ),
+            (
+                "18446744073709551616 / 2",
+                Ok(Object::Float(9223372036854775808.0)),
+            ),
+            (
+                "18446744073709551616 + 0.5",
+                Ok(Object::Float(18446744073709551616.0)),
+            ),
+            (
+                "18446744073709551616 > 9223372036854775807",
+                Ok(Object::Bool(true)),
+            ),
+            ("-18446744073709551616 < 1", Ok(Object::Bool(true))),
+            (
+                "18446744073709551616 == 18446744073709551616",
+                Ok(Object::Bool(true)),
+            ),
+            ("18446744073709551616 >= 1.5", Ok(Object::Bool(true))),
+            // exact: 2^53 + 1 is not equal to 2^53 even though their f64s are
+            (
+                "9007199254740993 == 9007199254740992",
+                Ok(Object::Bool(false)),
+            ),
+            (
+                "18446744073709551616 ~/ 0",
+                Err(EvalError::DivideByZero("right: 0 num is zero".to_string())),
+            ),
+        ];
+
+        for (input, expect) in inputs {
+            let v = eval_input(&mut intpter, input);
+            println!("{} => {:?}", input, v);
+            assert_eq!(expect, v);
+        }
+
+        let buf = SharedBuf(Rc::new(RefCell::new(vec![])));
+        let mut intpter = Interpreter::with_output(Box::new(buf.clone()));
+        let input = r#"
+        var n = 1
+        for i in 0..100 {
+            n = n * 2
+        }
+        print(n, (n,), !!n)
+        "#;
+        assert!(eval_input(&mut intpter, input).is_ok());
+        assert_eq!(
+            "1267650600228229401496703205376 (1267650600228229401496703205376,) true\n",
+            String::from_utf8(buf.0.borrow().clone()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_eval_modulo_power_bitwise() {
+        let big = |s: &str| Object::BigInt(s.parse::<BigInt>().unwrap());
+        let mut intpter = Interpreter::new();
+        let inputs = vec![
+            ("7 % 3", Ok(Object::Integer(1))),
+            ("-7 % 3", Ok(Object::Integer(2))),
+            ("7 % -3", Ok(Object::Integer(-2))),
+            ("7.5 % 2", Ok(Object::Float(1.5))),
+            ("-7.5 % 2", Ok(Object::Float(0.5))),
+            ("2 ** 10", Ok(Object::Integer(1024))),
+            ("2 ** 3 ** 2", Ok(Object::Integer(512))),
+            ("-2 ** 2", Ok(Object::Integer(-4))),
+            ("2 ** -1", Ok(Object::Float(0.5))),
+            ("4 ** 0.5", Ok(Object::Float(2.0))),
+            ("2 ** 64", Ok(big("18446744073709551616"))),
+            ("2 ** 64 % 1000", Ok(Object::Integer(616))),
+            ("(-1) ** 4000000001", Ok(Object::Integer(-1))),
+            (
+                "3 ** 4000000000",
+                Err(EvalError::OperandOutOfRange(
+                    Token::DoubleStar,
+                    Object::Integer(4000000000),
+                )),
+            ),
+            ("1 + 2 * 3 ** 2 % 5", Ok(Object::Integer(4))),
+            ("12 & 10", Ok(Object::Integer(8))),
+            ("12 | 10", Ok(Object::Integer(14))),
+            ("12 ^ 10", Ok(Object::Integer(6))),
+            ("~5", Ok(Object::Integer(-6))),
+            ("1 << 4", Ok(Object::Integer(16))),
+            ("-16 >> 2", Ok(Object::Integer(-4))),
+            ("1 >> 100", Ok(Object::Integer(0))),
+            ("1 << 63", Ok(big("9223372036854775808"))),
+            ("(1 << 64) >> 60", Ok(Object::Integer(16))),
+            ("(1 << 64) | 1", Ok(big("18446744073709551617"))),
+            ("(1 << 64) & 255", Ok(Object::Integer(0))),
+            ("~(1 << 64)", Ok(big("-18446744073709551617"))),
+            // bitwise binds looser than arithmetic and tighter than comparison
+            ("1 | 2 == 3", Ok(Object::Bool(true))),
+            ("1 + 1 << 2", Ok(Object::Integer(8))),
+            (
+                "5 % 0",
+                Err(EvalError::DivideByZero("right: 0 num is zero".to_string())),
+            ),
+            (
+                "1.5 & 1",
+                Err(EvalError::NotInteger(Token::BitAnd, Object::Float(1.5))),
+            ),
+            (
+                "1 << 2.0",
+                Err(EvalError::NotInteger(Token::ShiftLeft, Object::Float(2.0))),
+            ),
+            (
+                "~1.5",
+                Err(EvalError::NotInteger(Token::BitNot, Object::Float(1.5))),
+            ),
+            ("0 << 4000000000", Ok(Object::Integer(0))),
+            (
+                "1 << 4000000000",
+                Err(EvalError::OperandOutOfRange(
+                    Token::ShiftLeft,
+                    Object::Integer(4000000000),
+                )),
+            ),
+            (
+                "1 << -1",
+                Err(EvalError::OperandOutOfRange(
+                    Token::ShiftLeft,
+                    Object::Integer(-1),
+                )),
+            ),
+        ];
+
+        for (input, expect) in inputs {
+            let v = eval_input(&mut intpter, input);
+            println!("{} => {:?}", input, v);
+            assert_eq!(expect, v);
+        }
+    }
+
+    #[test]
+    fn test_eval_collection_equality() {
+        let mut intpter = Interpreter::new();
+        let inputs = vec![
+            ("1 == 1.0", true),
+            ("(1,) == (1.0,)", true),
+            ("[1] == [1.0]", true),
+            ("[1] != [1.0]", false),
+            ("[(1, [2])] == [(1.0, [2.0])]", true),
+            ("({\"a\": 1} == {\"a\": 1.0})", true),
+            ("[18446744073709551616] == [18446744073709551616.0]", true),
+            ("[9007199254740993] == [9007199254740992]", false),
+            ("[1] == [1.5]", false),
+            ("[1] == [1, 2]", false),
+            ("[1] == (1,)", false),
+            ("[\"1\"] == [1]", false),
+            ("({\"a\": 1} == {\"b\": 1})", false),
+        ];
+
+        for (input, expect) in inputs {
+            let v = eval_input(&mut intpter, input);
+            println!("{} => {:?}", input, v);
+            assert_eq!(Ok(Object::Bool(expect)), v);
+        }
+    }
+
+    #[test]
+    fn test_eval_list_map() {
+        let buf = SharedBuf(Rc::new(RefCell::new(vec![])));
+        let mut intpter = Interpreter::with_output(Box::new(buf.clone()));
+        let input = r#"
+        var xs = [1, "a", (2, 3),]
+        var m = {"b": 2, "a": 1, (1, 2): [3]}
+        var ys = xs
+        ys[0] = 100
+        m["c"] = xs[-1]
+        print(xs)
+        print(m, m["a"], m[(1, 2)][0])
+        for k in {"x": 1, "y": 2} {
+            print(k)
+        }
+        var (p, q) = [7, 8]
+        print(p + q, [] == [], [1, [2]] == [1, [2]], !![], !!{})
+        "#;
+        let res = eval_input(&mut intpter, input);
+        println!("{:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            concat!(
+                "[100, \"a\", (2, 3)]\n",
+                "{\"a\": 1, \"b\": 2, \"c\": (2, 3), (1, 2): [3]} 1 3\n",
+                "x\n",
+                "y\n",
+                "15 true true false false\n",
+            ),
+            String::from_utf8(buf.0.borrow().clone()).unwrap()
+        );
+
+        let mut intpter = Interpreter::new();
+        let inputs = vec![
+            ("[1, 2][2]", Err(EvalError::IndexOutOfRange(2, 2))),
+            (
+                "[1, 2][1.0]",
+                Err(EvalError::InvalidIndex(Object::Float(1.0))),
+            ),
+            (
+                r#"var m = {"a": 1} m["b"]"#,
+                Err(EvalError::KeyNotFound(Object::SString("b".to_string()))),
+            ),
+            (
+                "var m = {1.5: 1}",
+                Err(EvalError::UnhashableKey(Object::Float(1.5))),
+            ),
+            (
+                "var t = (1, 2) t[0] = 3",
+                Err(EvalError::NotAssignable(Object::Tuple(vec![
+                    Object::Integer(1),
+                    Object::Integer(2),
+                ]))),
+            ),
+        ];
+
+        for (input, expect) in inputs {
+            let v = eval_input(&mut intpter, input);
+            println!("{} => {:?}", input, v);
+            assert_eq!(expect, v);
+        }
+    }
+
+    #[test]
+    fn test_eval_compound_assign() {
+        let buf = SharedBuf(Rc::new(RefCell::new(vec![])));
+        let mut intpter = Interpreter::with_output(Box::new(buf.clone()));
+        let input = r#"
+        class Point {
+            def init(x) {
+                this.x = x
+            }
+        }
+        var calls = 0
+        var p = Point(1)
+        var xs = [10, 20, 30]
+        var m = {"n": 5}
+        def idx() {
+            calls += 1
+            return 1
+        }
+        def point() {
+            calls += 1
+            return p
+        }
+        var n = 10
+        n += 5
+        n -= 3
+        n *= 2
+        n %= 7
+        var f = 9
+        f /= 2
+        xs[idx()] += 5
+        point().x *= 4
+        m["n"] -= 1
+        print(n, f, xs, p.x, m, calls)
+        "#;
+        let res = eval_input(&mut intpter, input);
+        println!("{:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            "3 4.5 [10, 25, 30] 4 {\"n\": 4} 2\n",
+            String::from_utf8(buf.0.borrow().clone()).unwrap()
+        );
+
+        let mut intpter = Interpreter::new();
+        let inputs = vec![
+            (
+                "missing += 1",
+                Err(EvalError::IdentNotFound("missing".to_string(), None)),
+            ),
+            (
+                r#"var m = {} m["k"] += 1"#,
+                Err(EvalError::KeyNotFound(Object::SString("k".to_string()))),
+            ),
+        ];
+
+        for (input, expect) in inputs {
+            let v = eval_input(&mut intpter, input);
+            println!("{} => {:?}", input, v);
+            assert_eq!(expect, v);
+        }
+    }
+
+    #[test]
+    fn test_eval_string_ops() {
+        let s = |v: &str| Object::SString(v.to_string());
+        let mut intpter = Interpreter::new();
+        let inputs = vec![
+            (r#""a" + "b""#, Ok(s("ab"))),
+            (r#""-" * 5"#, Ok(s("-----"))),
+            (r#"3 * "ab""#, Ok(s("ababab"))),
+            (r#""ab" * -1"#, Ok(s(""))),
+            (r#""" * 9223372036854775807"#, Ok(s(""))),
+            (
+                r#""ab" * 9223372036854775807"#,
+                Err(EvalError::OperandOutOfRange(
+                    Token::Star('*'),
+                    Object::Integer(9223372036854775807),
+                )),
+            ),
+            (
+                r#"268435456 * "ab""#,
+                Err(EvalError::OperandOutOfRange(
+                    Token::Star('*'),
+                    Object::Integer(268435456),
+                )),
+            ),
+            (r#""n=" + 5"#, Ok(s("n=5"))),
+            (r#"2.0 + " apples""#, Ok(s("2.0 apples"))),
+            (r#""t=" + (1, "a") + true"#, Ok(s("t=(1, \"a\")true"))),
+            (r#"var x = "a" x += "b" x *= 2"#, Ok(s("abab"))),
+            (r#""a" == 1"#, Ok(Object::Bool(false))),
+            (r#""1" != 1"#, Ok(Object::Bool(true))),
+            (r#""abc" < "abd""#, Ok(Object::Bool(true))),
+            (
+                r#""a" - 1"#,
+                Err(EvalError::InvalidOperands(
+                    Token::Minus('-'),
+                    s("a"),
+                    Object::Integer(1),
+                )),
+            ),
+            (
+                r#""a" * "b""#,
+                Err(EvalError::InvalidOperands(Token::Star('*'), s("a"), s("b"))),
+            ),
+            (
+                r#""a" * 1.5"#,
+                Err(EvalError::InvalidOperands(
+                    Token::Star('*'),
+                    s("a"),
+                    Object::Float(1.5),
+                )),
+            ),
+            (
+                r#""a" < 1"#,
+                Err(EvalError::DifferObjectToCompare(s("a"), Object::Integer(1))),
+            ),
+        ];
+
+        for (input, expect) in inputs {
+            let v = eval_input(&mut intpter, input);
+            println!("{} => {:?}", input, v);
+            assert_eq!(expect, v);
+        }
+    }
+
+    #[test]
+    fn test_eval_interpolation() {
+        let s = |v: &str| Object::SString(v.to_string());
+        let mut intpter = Interpreter::new();
+        let inputs = vec![
+            (
+                r#"var name = "sip" var n = 2 "hello ${name}, you have ${n + 1} messages""#,
+                Ok(s("hello sip, you have 3 messages")),
+            ),
+            (
+                r#""${1.0} ${(1, "a")} ${[true]}""#,
+                Ok(s("1.0 (1, \"a\") [true]")),
+            ),
+            (r#""${"in" + "ner ${n}"}!""#, Ok(s("inner 2!"))),
+            (r#"var f = x => x * 2 "${f(21)}""#, Ok(s("42"))),
+            (
+                r#""${missing}""#,
+                Err(EvalError::IdentNotFound("missing".to_string(), None)),
+            ),
+        ];
+
+        for (input, expect) in inputs {
+            let v = eval_input(&mut intpter, input);
+            println!("{} => {:?}", input, v);
+            assert_eq!(expect, v);
+        }
+
+        let tokens = Lexer::new(r#""${1 2}""#.to_string()).scan_tokens().unwrap();
+        assert!(Parser::new(tokens).parse().is_err());
+    }
+
+    #[test]
+    fn test_eval_error_span() {
+        let inputs = vec![
+            (
+                "var x = 1\nprint(x + y)",
+                (2, 11),
+                EvalError::IdentNotFound("y".to_string(), None),
+            ),
+            (
+                "var a = 1 / 0",
+                (1, 11),
+                EvalError::DivideByZero("right: 0 num is zero".to_string()),
+            ),
+            (
+                "def f(a) { return a }\n  f(1, 2)",
+                (2, 3),
+                EvalError::ArgumentsMismatch("f".to_string(), 1, 2),
+            ),
+            // an error inside a function points into its body, not at the call
+            (
+                "def f() {\n  return [1][3]\n}\nf()",
+                (2, 10),
+                EvalError::IndexOutOfRange(3, 1),
+            ),
+            (
+                "var s = \"a\"\ns -= 1",
+                (2, 3),
+                EvalError::InvalidOperands(
+                    Token::Minus('-'),
+                    Object::SString("a".to_string()),
+                    Object::Integer(1),
+                ),
+            ),
+        ];
+
+        for (input, (line, column), expect) in inputs {
+            let (tokens, spans) = Lexer::with_file(input.to_string(), "t.sip")
+                .scan_tokens_with_spans()
+                .unwrap();
+            let program = Parser::with_spans(tokens, spans).parse().unwrap();
+            let err = Interpreter::new().eval_program(program).unwrap_err();
+            println!("{:?} => {:?}", input, err);
+
+            let span = err.span().unwrap().clone();
+            assert_eq!(
+                ("t.sip", line, column),
+                (&*span.file, span.line, span.column)
+            );
+            assert_eq!(expect.to_string(), err.to_string());
+            assert_eq!(expect, err.into_kind());
+        }
+    }
+
+    #[test]
+    fn test_eval_statement_ends() {
+        let eval = |input: &str| {
+            let (tokens, spans) = Lexer::new(input.to_string())
+                .scan_tokens_with_spans()
+                .unwrap();
+            let program = Parser::with_spans(tokens, spans).parse().unwrap();
+            Interpreter::new().eval_program(program)
+        };
+
+        let inputs = vec![
+            ("var x = 1\n-2\nx", Object::Integer(1)),
+            ("var x = 1 -\n2\nx", Object::Integer(-1)),
+            ("var x = (1\n-2)\nx", Object::Integer(-1)),
+            ("var xs = [5, 6]\nxs\n[0][0]", Object::Integer(0)),
+            ("var a = 1; var b = 2; a + b", Object::Integer(3)),
+            ("def f() {\n  return\n  1\n}\nf()", Object::Null),
+            ("def f() { return 1 } f() + 1", Object::Integer(2)),
+        ];
+        for (input, expect) in inputs {
+            let res = eval(input);
+            println!("{:?} => {:?}", input, res);
+            assert_eq!(Ok(expect), res);
+        }
+    }
+
+    #[test]
+    fn test_eval_recursion_limit() {
+        // like main, give the interpreter a stack that fits MAX_CALL_DEPTH calls
+        let runner = std::thread::Builder::new()
+            .stack_size(256 * 1024 * 1024)
+            .spawn(|| {
+                let mut intpter = Interpreter::new();
+                let def = "def f(n) { if (n == 0) { return 0 } return 1 + f(n - 1) }";
+                eval_input(&mut intpter, def).unwrap();
+
+                let deepest = format!("f({})", MAX_CALL_DEPTH - 1);
+                let v = eval_input(&mut intpter, &deepest);
+                assert_eq!(Ok(Object::Integer(MAX_CALL_DEPTH as i64 - 1)), v);
+
+                let too_deep = format!("f({})", MAX_CALL_DEPTH);
+                let err = eval_input(&mut intpter, &too_deep).unwrap_err();
+                assert_eq!(EvalError::RecursionLimit(MAX_CALL_DEPTH), err);
+
+                // the depth is unwound with the error
+                assert_eq!(Ok(Object::Integer(3)), eval_input(&mut intpter, "f(3)"));
+            })
+            .unwrap();
+        runner.join().unwrap();
+    }
 }
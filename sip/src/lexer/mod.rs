@@ -1,43 +1,161 @@
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+
 use crate::{
     errors::LexerError,
-    tokens::{self, Token},
+    span::Span,
+    tokens::{self, StrPart, Token},
 };
 
 pub struct Lexer {
     chars: Vec<char>,
     start: usize,
     current: usize,
+    file: Rc<str>,
+    offsets: Vec<usize>,     // byte offset of each char, plus one for the end
+    line_starts: Vec<usize>, // index of the first char of each line
+    errors: Vec<LexerError>, // errors recovered from so far
 }
 
 impl Lexer {
+    #[cfg(test)]
     pub fn new(text: String) -> Self {
-        let text_clone = text.clone();
+        Self::with_file(text, "<input>")
+    }
+
+    // file is only used to label the spans, e.g. in error messages
+    pub fn with_file(text: String, file: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let mut offsets = Vec::with_capacity(chars.len() + 1);
+        let mut line_starts = vec![0];
+        let mut offset = 0;
+        for (i, c) in chars.iter().enumerate() {
+            offsets.push(offset);
+            offset += c.len_utf8();
+            if *c == '\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        offsets.push(offset);
+
         Self {
-            start: 0 as usize,
-            current: 0 as usize,
-            chars: text_clone.chars().collect(),
+            start: 0,
+            current: 0,
+            chars,
+            file: Rc::from(file),
+            offsets,
+            line_starts,
+            errors: vec![],
         }
     }
 
+    #[cfg(test)]
     pub fn scan_tokens(&mut self) -> Result<Vec<Token>, LexerError> {
+        let (tokens, _) = self.scan_tokens_with_spans()?;
+        Ok(tokens)
+    }
+
+    // the same tokens as scan_tokens, with the span of each token at the same index
+    #[cfg(test)]
+    pub fn scan_tokens_with_spans(&mut self) -> Result<(Vec<Token>, Vec<Span>), LexerError> {
+        let (tokens, spans, mut errors) = self.scan_tokens_with_errors();
+        if errors.is_empty() {
+            return Ok((tokens, spans));
+        }
+        Err(errors.remove(0))
+    }
+
+    // scan the whole input, whatever could not be lexed becomes a Token::Unkown and
+    // the errors come back in source order next to the tokens and their spans
+    pub fn scan_tokens_with_errors(&mut self) -> (Vec<Token>, Vec<Span>, Vec<LexerError>) {
         let mut tokens: Vec<Token> = vec![];
+        let mut spans: Vec<Span> = vec![];
+        // a #! line at the very top lets a script be run directly
+        if self.chars.starts_with(&['#', '!']) {
+            self.skip_line();
+        }
         loop {
             if self.is_at_end() {
                 tokens.push(Token::EOF);
+                spans.push(self.eof_span());
                 break;
             }
 
             self.start = self.current;
-            let tk = self.next_token()?;
-            match tk {
-                Token::WhiteSpace => {}
-                _ => {
-                    tokens.push(tk.clone());
+            match self.next_token() {
+                Ok(Token::WhiteSpace) => {}
+                Ok(tk) => {
+                    tokens.push(tk);
+                    spans.push(self.span(self.start, self.current));
+                }
+                // the comment ran to the end, there is nothing left to lex
+                Err(e @ LexerError::UnterminatedComment(_)) => self.errors.push(e),
+                Err(e) => {
+                    // an unterminated string only takes the rest of its line, errors
+                    // found past that are found again when the next lines are lexed
+                    if let LexerError::InvalidString(..) = e {
+                        self.current = self.line_end(self.start);
+                        let end = self.offsets[self.current];
+                        self.errors.retain(|e| e.span().offset < end);
+                    }
+                    tokens.push(Token::Unkown);
+                    spans.push(self.span(self.start, self.current));
+                    self.errors.push(e);
                 }
             }
         }
 
-        Ok(tokens)
+        let mut errors = std::mem::take(&mut self.errors);
+        errors.sort_by_key(|e| e.span().offset);
+        (tokens, spans, errors)
+    }
+
+    // index of the newline ending the line that from is on
+    fn line_end(&self, from: usize) -> usize {
+        match self.chars[from..].iter().position(|c| *c == '\n') {
+            Some(i) => from + i,
+            None => self.chars.len(),
+        }
+    }
+
+    // the end of the input. after a final line break that would be a line past the last
+    // one, so it is put at the end of the last line instead
+    fn eof_span(&self) -> Span {
+        let mut end = self.chars.len();
+        if self.chars[..end].ends_with(&['\n']) {
+            end -= 1;
+        }
+        if self.chars[..end].ends_with(&['\r']) {
+            end -= 1;
+        }
+        self.span(end, end)
+    }
+
+    // the span of the chars in start..end
+    fn span(&self, start: usize, end: usize) -> Span {
+        let end = end.min(self.chars.len());
+        let line = self.line_starts.partition_point(|s| *s <= start);
+        let column = start - self.line_starts[line - 1] + 1;
+        let offset = self.offsets[start];
+        let mut span = Span::new(
+            self.file.clone(),
+            line,
+            column,
+            offset,
+            self.offsets[end] - offset,
+        );
+        // the line of the last char, an empty span ends where it starts
+        let last = end.max(start + 1) - 1;
+        span.end_line = self.line_starts.partition_point(|s| *s <= last);
+        span
+    }
+
+    // the string starting at from runs off the end of the input
+    // only the first line of the string is shown, the rest of the file is not part of it
+    fn unterminated_string(&self, from: usize) -> LexerError {
+        let str_content = String::from_iter(&self.chars[from..self.line_end(from)]);
+        LexerError::InvalidString(str_content, self.span(from, from + 1))
     }
 
     fn next_token(&mut self) -> Result<Token, LexerError> {
@@ -51,10 +169,63 @@ impl Lexer {
             '}' => Ok(Token::RBrace('}')),
             '[' => Ok(Token::LSBracket('[')),
             ']' => Ok(Token::RSBracket(']')),
-            '+' => Ok(Token::Plus(ch)),
-            '-' => Ok(Token::Minus(ch)),
-            '*' => Ok(Token::Star(ch)),
-            '/' => Ok(Token::Slash(ch)),
+            '+' => {
+                if self.is_current_match('=') {
+                    return Ok(Token::PlusAssign);
+                }
+                Ok(Token::Plus(ch))
+            }
+            '-' => {
+                if self.is_current_match('=') {
+                    return Ok(Token::MinusAssign);
+                }
+                Ok(Token::Minus(ch))
+            }
+            '*' => {
+                if self.is_current_match('*') {
+                    return Ok(Token::DoubleStar);
+                }
+                if self.is_current_match('=') {
+                    return Ok(Token::StarAssign);
+                }
+                Ok(Token::Star(ch))
+            }
+            '%' => {
+                if self.is_current_match('=') {
+                    return Ok(Token::PercentAssign);
+                }
+                Ok(Token::Percent(ch))
+            }
+            '^' => Ok(Token::BitXor),
+            '~' => {
+                if self.is_current_match('/') {
+                    return Ok(Token::TildeSlash);
+                }
+                Ok(Token::BitNot)
+            }
+            '/' => {
+                if self.is_current_match('/') {
+                    self.skip_line();
+                    return Ok(Token::WhiteSpace);
+                }
+                if self.is_current_match('*') {
+                    self.skip_block_comment()?;
+                    return Ok(Token::WhiteSpace);
+                }
+                if self.is_current_match('=') {
+                    return Ok(Token::SlashAssign);
+                }
+                Ok(Token::Slash(ch))
+            }
+            ';' => Ok(Token::Semicolon),
+            ':' => Ok(Token::Colon),
+            ',' => Ok(Token::Comma),
+            '.' => {
+                if self.is_current_match('.') {
+                    return Ok(Token::DotDot);
+                }
+                Ok(Token::Dot)
+            }
             '!' => {
                 if self.is_current_match('=') {
                     return Ok(Token::NotEQ("!=".to_string()));
@@ -64,6 +235,8 @@ impl Lexer {
             '=' => {
                 if self.is_current_match('=') {
                     Ok(Token::EQ("==".to_string()))
+                } else if self.is_current_match('>') {
+                    Ok(Token::Arrow)
                 } else {
                     Ok(Token::Assign(ch))
                 }
@@ -71,6 +244,8 @@ impl Lexer {
             '<' => {
                 if self.is_current_match('=') {
                     Ok(Token::LtEQ("<=".to_string()))
+                } else if self.is_current_match('<') {
+                    Ok(Token::ShiftLeft)
                 } else {
                     Ok(Token::Lt("<".to_string()))
                 }
@@ -78,6 +253,8 @@ impl Lexer {
             '>' => {
                 if self.is_current_match('=') {
                     Ok(Token::GtEQ(">=".to_string()))
+                } else if self.is_current_match('>') {
+                    Ok(Token::ShiftRight)
                 } else {
                     Ok(Token::Gt(">".to_string()))
                 }
@@ -96,9 +273,14 @@ impl Lexer {
                     Ok(Token::BitAnd)
                 }
             }
-            '"' => self.parse_string(),
+            '"' => self.parse_string(false),
+            // r"..." is a raw string, backslashes are kept as they are
+            'r' if self.peek() == Some('"') => {
+                self.advance();
+                self.parse_string(true)
+            }
             _ => {
-                if ch.is_digit(10) {
+                if ch.is_ascii_digit() {
                     let tk = self.parse_number()?;
                     return Ok(tk);
                 }
@@ -108,7 +290,10 @@ impl Lexer {
                     return Ok(tk);
                 }
 
-                Err(LexerError::InvalidToken(ch))
+                Err(LexerError::InvalidToken(
+                    ch,
+                    self.span(self.start, self.current),
+                ))
             }
         }
     }
@@ -143,14 +328,56 @@ impl Lexer {
         Some(self.chars[self.current])
     }
 
+    fn peek_next(&self) -> Option<char> {
+        if self.current + 1 >= self.chars.len() {
+            return None;
+        }
+
+        Some(self.chars[self.current + 1])
+    }
+
+    // skips up to the end of the line, the newline itself is left for the next token
+    fn skip_line(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    // the opening /* is already consumed, block comments nest: /* a /* b */ c */
+    fn skip_block_comment(&mut self) -> Result<(), LexerError> {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                return Err(LexerError::UnterminatedComment(
+                    self.span(self.start, self.start + 2),
+                ));
+            }
+
+            let c = self.advance();
+            if c == '/' && self.is_current_match('*') {
+                depth += 1;
+            } else if c == '*' && self.is_current_match('/') {
+                depth -= 1;
+            }
+        }
+        Ok(())
+    }
+
     fn parse_number(&mut self) -> Result<Token, LexerError> {
         let mut is_float = false;
         while let Some(d) = self.peek() {
-            if d.eq(&'.') || d.is_digit(10) {
+            if d.is_ascii_digit() {
                 self.advance();
-                if d.eq(&'.') {
-                    is_float = true;
-                }
+            } else if d.eq(&'.')
+                && !is_float
+                && self.peek_next().is_some_and(|c| c.is_ascii_digit())
+            {
+                // a `.` not followed by a digit belongs to the next token, e.g. `0..10`
+                self.advance();
+                is_float = true;
             } else {
                 break;
             }
@@ -158,39 +385,194 @@ impl Lexer {
 
         let num_text = String::from_iter(&self.chars[self.start..self.current]);
 
+        let span = self.span(self.start, self.current);
         if is_float {
             let num_res = num_text.parse::<f64>();
             let res = match num_res {
                 Ok(n) => Ok(Token::Float(n)),
-                Err(e) => Err(LexerError::InvalidNum(e.to_string())),
+                Err(e) => Err(LexerError::InvalidNum(e.to_string(), span)),
             };
             return res;
         }
 
-        let num_res = num_text.parse::<i64>();
-        match num_res {
-            Ok(n) => Ok(Token::Integer(n)),
-            Err(e) => Err(LexerError::InvalidNum(e.to_string())),
+        if let Ok(n) = num_text.parse::<i64>() {
+            return Ok(Token::Integer(n));
+        }
+
+        // too big for an i64, keep it exact as a BigInt
+        match num_text.parse::<BigInt>() {
+            Ok(n) => Ok(Token::BigInteger(n)),
+            Err(e) => Err(LexerError::InvalidNum(e.to_string(), span)),
         }
     }
 
-    fn parse_string(&mut self) -> Result<Token, LexerError> {
-        let mut valid_str = false;
-        while let Some(c) = self.peek() {
+    // the opening " is already consumed. """ opens a multi-line string that runs to the
+    // next """, so it can hold single quotes and newlines as they are. ${expr} embeds an
+    // expression, except in raw strings
+    fn parse_string(&mut self, raw: bool) -> Result<Token, LexerError> {
+        let triple = self.peek() == Some('"') && self.peek_next() == Some('"');
+        if triple {
             self.advance();
-            if c.eq(&'"') {
-                valid_str = true;
-                break;
+            self.advance();
+        }
+
+        let mut parts: Vec<StrPart> = vec![];
+        let mut str_content = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(self.unterminated_string(self.start)),
+            };
+            self.advance();
+
+            if c == '"' {
+                if !triple {
+                    break;
+                }
+                // in a run of quotes the last three close the string, so """" ends in a quote
+                if self.peek() == Some('"')
+                    && self.peek_next() == Some('"')
+                    && self.chars.get(self.current + 2) != Some(&'"')
+                {
+                    self.advance();
+                    self.advance();
+                    break;
+                }
             }
+
+            if c == '\\' && !raw {
+                // a bad escape is reported but doesn't end the string
+                match self.parse_escape() {
+                    Ok(c) => str_content.push(c),
+                    Err(e @ LexerError::InvalidEscape(..)) => self.errors.push(e),
+                    Err(e) => return Err(e),
+                }
+                continue;
+            }
+            if c == '$' && !raw && self.is_current_match('{') {
+                if !str_content.is_empty() {
+                    parts.push(StrPart::Text(std::mem::take(&mut str_content)));
+                }
+                parts.push(StrPart::Expr(self.scan_interpolation()?));
+                continue;
+            }
+            str_content.push(c);
         }
 
-        if self.is_at_end() && !valid_str {
-            let str_content = String::from_iter(&self.chars[self.start..]);
-            return Err(LexerError::InvalidString(str_content));
+        if parts.is_empty() {
+            return Ok(Token::SString(str_content));
+        }
+        if !str_content.is_empty() {
+            parts.push(StrPart::Text(str_content));
         }
+        Ok(Token::Interpolated(parts))
+    }
 
-        let str_content = String::from_iter(&self.chars[self.start + 1..self.current - 1]);
-        Ok(Token::SString(str_content))
+    // lex the expression inside ${...} up to the } that closes it, nested braces and
+    // strings are handled by next_token as usual
+    fn scan_interpolation(&mut self) -> Result<Vec<Token>, LexerError> {
+        let str_start = self.start;
+        let mut tokens: Vec<Token> = vec![];
+        let mut depth = 0;
+        loop {
+            if self.is_at_end() {
+                self.start = str_start;
+                return Err(self.unterminated_string(str_start));
+            }
+
+            self.start = self.current;
+            let tk = match self.next_token() {
+                Ok(tk) => tk,
+                Err(e @ LexerError::InvalidString(..)) => {
+                    self.start = str_start;
+                    return Err(e);
+                }
+                // the rest of the string is still lexed
+                Err(e) => {
+                    self.errors.push(e);
+                    Token::Unkown
+                }
+            };
+            match tk {
+                Token::WhiteSpace => {}
+                Token::LBrace(c) => {
+                    depth += 1;
+                    tokens.push(Token::LBrace(c));
+                }
+                Token::RBrace(_) if depth == 0 => break,
+                Token::RBrace(c) => {
+                    depth -= 1;
+                    tokens.push(Token::RBrace(c));
+                }
+                tk => tokens.push(tk),
+            }
+        }
+
+        self.start = str_start;
+        tokens.push(Token::EOF);
+        Ok(tokens)
+    }
+
+    // the \ is already consumed
+    fn parse_escape(&mut self) -> Result<char, LexerError> {
+        let begin = self.current - 1;
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(self.unterminated_string(self.start)),
+        };
+        self.advance();
+
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '$' => Ok('$'),
+            'u' => self.parse_unicode_escape(begin),
+            _ => Err(LexerError::InvalidEscape(
+                format!("\\{}", c),
+                self.span(begin, self.current),
+            )),
+        }
+    }
+
+    // \u{1F600}: one to six hex digits naming a unicode scalar value, escape_start is
+    // the index of the backslash
+    fn parse_unicode_escape(&mut self, escape_start: usize) -> Result<char, LexerError> {
+        if !self.is_current_match('{') {
+            return Err(LexerError::InvalidEscape(
+                "\\u".to_string(),
+                self.span(escape_start, self.current),
+            ));
+        }
+
+        let begin = self.current;
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_hexdigit() {
+                break;
+            }
+            self.advance();
+        }
+        let digits = String::from_iter(&self.chars[begin..self.current]);
+        if !self.is_current_match('}') || digits.is_empty() || digits.len() > 6 {
+            return Err(LexerError::InvalidEscape(
+                format!("\\u{{{}", digits),
+                self.span(escape_start, self.current),
+            ));
+        }
+
+        match u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+        {
+            Some(c) => Ok(c),
+            None => Err(LexerError::InvalidEscape(
+                format!("\\u{{{}}}", digits),
+                self.span(escape_start, self.current),
+            )),
+        }
     }
 
     fn parse_ident(&mut self) -> Result<Token, LexerError> {
@@ -213,20 +595,22 @@ impl Lexer {
 #[cfg(test)]
 mod tests {
     use super::Lexer;
-    use crate::tokens::Token;
+    use crate::tokens::{StrPart, Token};
+    use num_bigint::BigInt;
 
     #[test]
     fn test_scan_tokens1() {
-        let input = " \n + - * \t /";
+        let input = " \n + - * \t / ~/";
         let mut lexer = Lexer::new(input.to_string());
         let tokens_res = lexer.scan_tokens();
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
                 Token::Plus('+'),
                 Token::Minus('-'),
                 Token::Star('*'),
                 Token::Slash('/'),
+                Token::TildeSlash,
                 Token::EOF,
             ],
             tokens_res.unwrap()
@@ -238,7 +622,7 @@ mod tests {
         let input = "( { [ ) } ]";
         let mut lexer = Lexer::new(input.to_string());
         let tokens_res = lexer.scan_tokens();
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
                 Token::LParent('('),
@@ -258,7 +642,7 @@ mod tests {
         let input = "< <= > >= ! !=";
         let mut lexer = Lexer::new(input.to_string());
         let tokens_res = lexer.scan_tokens();
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
                 Token::Lt('<'.to_string()),
@@ -278,14 +662,10 @@ mod tests {
         let input = "100 256";
         let mut lexer = Lexer::new(input.to_string());
         let tokens_res = lexer.scan_tokens();
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         println!("{:?}", tokens_res);
         assert_eq!(
-            vec![
-                Token::Integer(100 as i64),
-                Token::Integer(256 as i64),
-                Token::EOF,
-            ],
+            vec![Token::Integer(100_i64), Token::Integer(256_i64), Token::EOF,],
             tokens_res.unwrap()
         );
     }
@@ -297,7 +677,7 @@ mod tests {
         let mut lexer = Lexer::new(input.to_string());
         let tokens_res = lexer.scan_tokens();
 
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         println!("{:?}", tokens_res);
         assert_eq!(
             vec![
@@ -318,12 +698,12 @@ mod tests {
         let tokens_res = lexer.scan_tokens();
         println!("{:?}", tokens_res);
 
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
                 Token::SString("Hello".to_string()),
                 Token::SString("World".to_string()),
-                Token::Integer(866 as i64),
+                Token::Integer(866_i64),
                 Token::EOF,
             ],
             tokens_res.unwrap()
@@ -338,7 +718,7 @@ mod tests {
         let tokens_res = lexer.scan_tokens();
         println!("{:?}", tokens_res);
 
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
                 Token::Ident("abc".to_string()),
@@ -357,7 +737,7 @@ mod tests {
         let tokens_res = lexer.scan_tokens();
         println!("{:?}", tokens_res);
 
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
                 Token::True,
@@ -372,13 +752,13 @@ mod tests {
 
     #[test]
     fn test_scan_keyword_tokens1() {
-        let input = "if else for while return def";
+        let input = "if else for while return def break continue class this super";
 
         let mut lexer = Lexer::new(input.to_string());
         let tokens_res = lexer.scan_tokens();
         println!("{:?}", tokens_res);
 
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
                 Token::If,
@@ -387,6 +767,11 @@ mod tests {
                 Token::While,
                 Token::Return,
                 Token::Def,
+                Token::Break,
+                Token::Continue,
+                Token::Class,
+                Token::This,
+                Token::Super,
                 Token::EOF,
             ],
             tokens_res.unwrap()
@@ -401,12 +786,12 @@ mod tests {
         let tokens_res = lexer.scan_tokens();
         println!("{:?}", tokens_res);
 
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
-                Token::Float(1.26 as f64),
-                Token::Float(0.6 as f64),
-                Token::Integer(123 as i64),
+                Token::Float(1.26_f64),
+                Token::Float(0.6_f64),
+                Token::Integer(123_i64),
                 Token::EOF,
             ],
             tokens_res.unwrap()
@@ -421,7 +806,7 @@ mod tests {
         let tokens_res = lexer.scan_tokens();
         println!("{:?}", tokens_res);
 
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
                 Token::BitOr,
@@ -442,7 +827,7 @@ mod tests {
         let tokens_res = lexer.scan_tokens();
         println!("{:?}", tokens_res);
 
-        assert_eq!(tokens_res.is_ok(), true);
+        assert!(tokens_res.is_ok());
         assert_eq!(
             vec![
                 Token::If,
@@ -468,4 +853,381 @@ mod tests {
             Err(e) => println!("err: {}", e),
         }
     }
+
+    #[test]
+    fn test_scan_for_range() {
+        let input = "for i in 0..10; 1.5..2 => ==";
+
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens_res = lexer.scan_tokens();
+        println!("{:?}", tokens_res);
+
+        assert!(tokens_res.is_ok());
+        assert_eq!(
+            vec![
+                Token::For,
+                Token::Ident("i".to_string()),
+                Token::In,
+                Token::Integer(0),
+                Token::DotDot,
+                Token::Integer(10),
+                Token::Semicolon,
+                Token::Float(1.5),
+                Token::DotDot,
+                Token::Integer(2),
+                Token::Arrow,
+                Token::EQ("==".to_string()),
+                Token::EOF
+            ],
+            tokens_res.unwrap()
+        )
+    }
+
+    #[test]
+    fn test_scan_big_integer() {
+        let input = "9223372036854775807 9223372036854775808";
+
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens_res = lexer.scan_tokens();
+        println!("{:?}", tokens_res);
+
+        assert_eq!(
+            vec![
+                Token::Integer(i64::MAX),
+                Token::BigInteger("9223372036854775808".parse::<BigInt>().unwrap()),
+                Token::EOF
+            ],
+            tokens_res.unwrap()
+        )
+    }
+
+    #[test]
+    fn test_scan_bitwise_ops() {
+        let input = "a % b ** c | d & e ^ ~f << g >> h <= i || j && k";
+
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens_res = lexer.scan_tokens();
+        println!("{:?}", tokens_res);
+
+        let ident = |s: &str| Token::Ident(s.to_string());
+        assert_eq!(
+            vec![
+                ident("a"),
+                Token::Percent('%'),
+                ident("b"),
+                Token::DoubleStar,
+                ident("c"),
+                Token::BitOr,
+                ident("d"),
+                Token::BitAnd,
+                ident("e"),
+                Token::BitXor,
+                Token::BitNot,
+                ident("f"),
+                Token::ShiftLeft,
+                ident("g"),
+                Token::ShiftRight,
+                ident("h"),
+                Token::LtEQ("<=".to_string()),
+                ident("i"),
+                Token::Or,
+                ident("j"),
+                Token::And,
+                ident("k"),
+                Token::EOF
+            ],
+            tokens_res.unwrap()
+        )
+    }
+
+    #[test]
+    fn test_scan_compound_assign() {
+        let input = "a += 1 -= *= ** /= ~/ %= {k: v}";
+
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens_res = lexer.scan_tokens();
+        println!("{:?}", tokens_res);
+
+        assert_eq!(
+            vec![
+                Token::Ident("a".to_string()),
+                Token::PlusAssign,
+                Token::Integer(1),
+                Token::MinusAssign,
+                Token::StarAssign,
+                Token::DoubleStar,
+                Token::SlashAssign,
+                Token::TildeSlash,
+                Token::PercentAssign,
+                Token::LBrace('{'),
+                Token::Ident("k".to_string()),
+                Token::Colon,
+                Token::Ident("v".to_string()),
+                Token::RBrace('}'),
+                Token::EOF
+            ],
+            tokens_res.unwrap()
+        )
+    }
+
+    #[test]
+    fn test_scan_string_escapes() {
+        let input = r##""a\"b\\c\n\t\u{48}\u{1F600}" r"C:\dir\n" "" """line "one"
+line\ttwo""" r"""raw "\n"""" "##;
+
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens_res = lexer.scan_tokens();
+        println!("{:?}", tokens_res);
+
+        assert_eq!(
+            vec![
+                Token::SString("a\"b\\c\n\tH\u{1F600}".to_string()),
+                Token::SString("C:\\dir\\n".to_string()),
+                Token::SString("".to_string()),
+                Token::SString("line \"one\"\nline\ttwo".to_string()),
+                Token::SString("raw \"\\n\"".to_string()),
+                Token::EOF
+            ],
+            tokens_res.unwrap()
+        );
+
+        // an identifier named r is still an identifier
+        let mut lexer = Lexer::new("r + 1".to_string());
+        assert_eq!(
+            vec![
+                Token::Ident("r".to_string()),
+                Token::Plus('+'),
+                Token::Integer(1),
+                Token::EOF
+            ],
+            lexer.scan_tokens().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_scan_string_errors() {
+        let inputs = vec![
+            (r#""a\qb""#, "unknown escape sequence: \\q"),
+            (r#""\u0041""#, "unknown escape sequence: \\u"),
+            (r#""\u{}""#, "unknown escape sequence: \\u{"),
+            (r#""\u{41""#, "unknown escape sequence: \\u{41"),
+            (r#""\u{D800}""#, "unknown escape sequence: \\u{D800}"),
+            (r#""\u{1234567}""#, "unknown escape sequence: \\u{1234567"),
+            (r#""abc"#, "\"abc"),
+            (r#""""abc""#, "\"\"\"abc\""),
+        ];
+
+        for (input, expect) in inputs {
+            let mut lexer = Lexer::new(input.to_string());
+            let res = lexer.scan_tokens();
+            println!("{} => {:?}", input, res);
+            assert_eq!(expect, res.unwrap_err().to_string());
+        }
+    }
+
+    #[test]
+    fn test_scan_interpolation() {
+        let input = r#""hi ${name}, ${n + 1} ${ {"k": "}"}["k"] }$ \${x}" r"${raw}""#;
+
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens_res = lexer.scan_tokens();
+        println!("{:?}", tokens_res);
+
+        assert_eq!(
+            vec![
+                Token::Interpolated(vec![
+                    StrPart::Text("hi ".to_string()),
+                    StrPart::Expr(vec![Token::Ident("name".to_string()), Token::EOF]),
+                    StrPart::Text(", ".to_string()),
+                    StrPart::Expr(vec![
+                        Token::Ident("n".to_string()),
+                        Token::Plus('+'),
+                        Token::Integer(1),
+                        Token::EOF
+                    ]),
+                    StrPart::Text(" ".to_string()),
+                    StrPart::Expr(vec![
+                        Token::LBrace('{'),
+                        Token::SString("k".to_string()),
+                        Token::Colon,
+                        Token::SString("}".to_string()),
+                        Token::RBrace('}'),
+                        Token::LSBracket('['),
+                        Token::SString("k".to_string()),
+                        Token::RSBracket(']'),
+                        Token::EOF
+                    ]),
+                    StrPart::Text("$ ${x}".to_string()),
+                ]),
+                Token::SString("${raw}".to_string()),
+                Token::EOF
+            ],
+            tokens_res.unwrap()
+        );
+
+        let mut lexer = Lexer::new(r#""a ${b""#.to_string());
+        assert!(lexer.scan_tokens().is_err());
+    }
+
+    #[test]
+    fn test_scan_comments() {
+        let input = r#"#!/usr/bin/env sip
+var x = 1 // the rest is ignored: "quote /*
+/* block /* nested */ still a comment */ x /= 2 /**/ ~x
+"// kept" / /*
+*/ 3"#;
+
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens_res = lexer.scan_tokens();
+        println!("{:?}", tokens_res);
+
+        assert_eq!(
+            vec![
+                Token::Var,
+                Token::Ident("x".to_string()),
+                Token::Assign('='),
+                Token::Integer(1),
+                Token::Ident("x".to_string()),
+                Token::SlashAssign,
+                Token::Integer(2),
+                Token::BitNot,
+                Token::Ident("x".to_string()),
+                Token::SString("// kept".to_string()),
+                Token::Slash('/'),
+                Token::Integer(3),
+                Token::EOF
+            ],
+            tokens_res.unwrap()
+        );
+
+        let inputs = vec![
+            "1 /* open",
+            "/* a /* b */",
+            "/*/",
+            // a shebang is only special on the first line
+            "1\n#!/usr/bin/env sip",
+        ];
+        for input in inputs {
+            let mut lexer = Lexer::new(input.to_string());
+            let res = lexer.scan_tokens();
+            println!("{:?} => {:?}", input, res);
+            assert!(res.is_err());
+        }
+
+        let mut lexer = Lexer::new("1 /* a /* b */".to_string());
+        assert_eq!(
+            "unterminated block comment",
+            lexer.scan_tokens().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_scan_spans() {
+        let input = "var é = \"a\"\n  x /* c */ >= 10";
+        let mut lexer = Lexer::with_file(input.to_string(), "main.sip");
+        let (tokens, spans) = lexer.scan_tokens_with_spans().unwrap();
+        assert_eq!(tokens.len(), spans.len());
+
+        // (line, column, byte offset, byte len)
+        let expect = vec![
+            (1, 1, 0, 3),
+            (1, 5, 4, 2),
+            (1, 7, 7, 1),
+            (1, 9, 9, 3),
+            (2, 3, 15, 1),
+            (2, 13, 25, 2),
+            (2, 16, 28, 2),
+            (2, 18, 30, 0),
+        ];
+        let got: Vec<_> = spans
+            .iter()
+            .map(|s| (s.line, s.column, s.offset, s.len))
+            .collect();
+        assert_eq!(expect, got);
+        assert_eq!("main.sip:2:13", spans[5].to_string());
+        assert_eq!(
+            &input[spans[5].offset..spans[5].offset + spans[5].len],
+            ">="
+        );
+
+        let inputs = vec![
+            ("1 +\n  @", (2, 3)),
+            ("x = \"ab\\q\"", (1, 8)),
+            ("\n  \"abc", (2, 3)),
+            ("1 /* a\n /* b */", (1, 3)),
+        ];
+        for (input, (line, column)) in inputs {
+            let mut lexer = Lexer::new(input.to_string());
+            let err = lexer.scan_tokens().unwrap_err();
+            println!("{:?} => {:?}", input, err);
+            assert_eq!((line, column), (err.span().line, err.span().column));
+        }
+    }
+
+    #[test]
+    fn test_scan_recover() {
+        let input = r#"var a = 1 @ 2
+var s = "x\qy${@}z"
+print("open
+var b = 3 # c"#;
+        let mut lexer = Lexer::new(input.to_string());
+        let (tokens, spans, errors) = lexer.scan_tokens_with_errors();
+        println!("{:?}\n{:?}", tokens, errors);
+        assert_eq!(tokens.len(), spans.len());
+
+        let got: Vec<_> = errors
+            .iter()
+            .map(|e| (e.span().line, e.span().column, e.to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                (1, 11, "@".to_string()),
+                (2, 11, "unknown escape sequence: \\q".to_string()),
+                (2, 16, "@".to_string()),
+                (3, 7, "\"open".to_string()),
+                (4, 11, "#".to_string()),
+            ],
+            got
+        );
+
+        assert_eq!(
+            vec![Token::Integer(1), Token::Unkown, Token::Integer(2)],
+            tokens[3..6]
+        );
+        assert_eq!(
+            Token::Interpolated(vec![
+                StrPart::Text("xy".to_string()),
+                StrPart::Expr(vec![Token::Unkown, Token::EOF]),
+                StrPart::Text("z".to_string()),
+            ]),
+            tokens[9]
+        );
+        // the unterminated string stops at the end of its line
+        assert_eq!(
+            vec![
+                Token::Print,
+                Token::LParent('('),
+                Token::Unkown,
+                Token::Var,
+                Token::Ident("b".to_string()),
+                Token::Assign('='),
+                Token::Integer(3),
+                Token::Unkown,
+                Token::Ident("c".to_string()),
+                Token::EOF,
+            ],
+            tokens[10..]
+        );
+        assert_eq!((3, 7, 5), (spans[12].line, spans[12].column, spans[12].len));
+
+        // an escape past the end of the string's line is lexed again as code
+        let mut lexer = Lexer::new("\"abc\n\\q".to_string());
+        let (_, _, errors) = lexer.scan_tokens_with_errors();
+        let got: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(vec!["\"abc", "\\"], got);
+
+        // scan_tokens still stops at the first error
+        let mut lexer = Lexer::new(input.to_string());
+        assert_eq!("@", lexer.scan_tokens().unwrap_err().to_string());
+    }
 }
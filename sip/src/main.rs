@@ -1,24 +1,56 @@
 mod ast;
+mod diagnostic;
+mod environment;
 mod errors;
 mod eval;
 mod lexer;
 mod object;
 mod parser;
+mod span;
 mod tokens;
 use lexer::Lexer;
 use std::env;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
+use std::thread;
 
-use crate::{eval::Interpreter, parser::Parser};
+use crate::{
+    ast::Program, diagnostic::Diagnostic, eval::Interpreter, object::Object, parser::Parser,
+};
+
+// evaluation recurses on the Rust stack, it has to fit eval::MAX_CALL_DEPTH calls
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() {
+    let runner = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("failed to start the interpreter thread");
+    if runner.join().is_err() {
+        std::process::exit(101);
+    }
+}
+
+fn run() {
     let args: Vec<String> = env::args().collect();
     println!("args: {:?}", args);
+    // sip check <file> reports every syntax error without running the file
+    if args.len() > 2 && args[1] == "check" {
+        match read_program(args[2].clone()) {
+            Ok(v) => {
+                if parse_program(&v, &args[2]).is_none() {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => println!("{:?}", e),
+        }
+        return;
+    }
+
     if args.len() > 1 {
         let program = read_program(args[1].clone());
         match program {
             Ok(v) => {
-                run_program(v);
+                run_program(v, &args[1]);
             }
             Err(e) => println!("{:?}", e),
         }
@@ -34,34 +66,61 @@ fn read_program(path: String) -> Result<String, std::io::Error> {
     Ok(content.to_string())
 }
 
-fn run_program(program: String) {
-    let mut lexer = Lexer::new(program);
-    let tokens = lexer.scan_tokens();
-    println!("{:?}", tokens);
-    match tokens {
-        Ok(tks) => {
-            let mut p = Parser::new(tks);
-            let program_res = p.parse();
-            if program_res.is_err() {
-                println!("parser err: {:?}", program_res.err());
-                return;
-            }
+fn run_program(program: String, file: &str) {
+    let parsed = match parse_program(&program, file) {
+        Some(v) => v,
+        None => return,
+    };
+    println!("{:?}", parsed);
+
+    let mut interpreter = Interpreter::new();
+    match interpreter.eval_program(parsed) {
+        Ok(result) => println!("result: {}", result),
+        Err(e) => report(Diagnostic::from(&e), &program),
+    }
+}
 
-            println!("{:?}", program_res);
+// lex and parse source, reporting all the syntax errors. None if there were any
+fn parse_program(source: &str, file: &str) -> Option<Program> {
+    let mut lexer = Lexer::with_file(source.to_string(), file);
+    let (tokens, spans, lexer_errors) = lexer.scan_tokens_with_errors();
+    for e in &lexer_errors {
+        report(Diagnostic::from(e), source);
+    }
 
-            let mut interpreter = Interpreter::new();
-            let result = interpreter.eval_program(program_res.ok().unwrap());
-            println!("result: {:?}", result);
-            return;
-        }
-        Err(e) => {
-            println!("lexer err: {:?}", e);
-        }
+    let (program, errors) = Parser::with_spans(tokens, spans).parse_with_errors();
+    for e in &errors {
+        report(Diagnostic::from(e), source);
+    }
+    if !lexer_errors.is_empty() || !errors.is_empty() {
+        return None;
+    }
+    Some(program)
+}
+
+// errors go to stderr, coloured when it is a terminal
+fn report(diagnostic: Diagnostic, source: &str) {
+    let color = std::io::stderr().is_terminal();
+    eprint!("{}", diagnostic.render(source, color));
+}
+
+// the input a diagnostic points into, by the <repl:N> its span is labelled with
+fn repl_source<'a>(sources: &'a [String], diagnostic: &Diagnostic) -> &'a str {
+    let index = diagnostic.span.as_ref().and_then(|span| {
+        let n = span.file.strip_prefix("<repl:")?.strip_suffix('>')?;
+        n.parse::<usize>().ok()?.checked_sub(1)
+    });
+    match index.and_then(|i| sources.get(i)) {
+        Some(source) => source,
+        None => sources.last().map_or("", |s| s.as_str()),
     }
 }
 
+// each input is its own file <repl:N>. the inputs are kept, so that an error in a
+// function defined by an earlier input shows the line it came from
 fn run_interactive_eval() {
     let mut interpreter = Interpreter::new();
+    let mut sources: Vec<String> = vec![];
 
     loop {
         print!(">>>");
@@ -69,31 +128,29 @@ fn run_interactive_eval() {
 
         let mut buf = String::new();
         match std::io::stdin().read_line(&mut buf) {
+            // end of input
+            Ok(0) => break,
             Ok(_) => {
-                let mut lexer = Lexer::new(buf);
-                let tokens = lexer.scan_tokens();
-                println!("tokens: {:?}", tokens);
-                match tokens {
-                    Ok(tks) => {
-                        let mut p = Parser::new(tks);
-                        let program_res = p.parse();
-                        if program_res.is_err() {
-                            println!("parser err: {:?}", program_res.err());
-                            continue;
-                        }
-
-                        println!("program: {:?}", program_res);
-                        let result = interpreter.eval_program(program_res.ok().unwrap());
-                        if result.is_err() {
-                            println!("failed to eval: {:?}", result.err());
-                            continue;
-                        }
-
-                        println!("{}", result.ok().unwrap());
-                    }
-                    Err(e) => {
-                        println!("lexer err: {}", e);
-                    }
+                sources.push(buf);
+                let buf = &sources[sources.len() - 1];
+                let file = format!("<repl:{}>", sources.len());
+                let program = match parse_program(buf, &file) {
+                    Some(v) => v,
+                    None => continue,
+                };
+
+                println!("program: {:?}", program);
+                let result = interpreter.eval_program(program);
+                if let Err(e) = result {
+                    let diagnostic = Diagnostic::from(&e);
+                    let source = repl_source(&sources, &diagnostic);
+                    report(diagnostic, source);
+                    continue;
+                }
+
+                let obj = result.ok().unwrap();
+                if obj != Object::Null {
+                    println!("{}", obj);
                 }
             }
             Err(e) => {
@@ -1,31 +1,354 @@
 use core::fmt;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::{ast::Node, environment::Environment};
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Object {
-    Class(String),
-    ClassInstance(String),
-    Function(String),
+    Class(Rc<Class>),
+    ClassInstance(Rc<Instance>),
+    Function(Rc<Function>),
     Integer(i64),
+    BigInt(BigInt), // only holds values outside the i64 range, see Object::from_bigint
     Float(f64),
-    Number(f64),
     Bool(bool),
     SString(String),
+    Range(i64, i64), // start..end, end is exclusive
+    Tuple(Vec<Object>),
+    List(Rc<RefCell<Vec<Object>>>), // shared and mutable, like class instances
+    Map(Rc<RefCell<BTreeMap<MapKey, Object>>>),
     Return(Rc<Object>),
+    Break,
+    Continue,
     Print(Vec<Object>),
-    Error(String),
     Null,
 }
 
+impl Object {
+    // null, false, zero, the empty string and empty collections are falsy,
+    // everything else is truthy
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Object::Null => false,
+            Object::Bool(v) => *v,
+            Object::Integer(v) => *v != 0,
+            Object::BigInt(v) => !v.is_zero(),
+            Object::Float(v) => *v != 0.0 && !v.is_nan(),
+            Object::SString(v) => !v.is_empty(),
+            Object::Range(start, end) => start < end,
+            Object::Tuple(items) => !items.is_empty(),
+            Object::List(items) => !items.borrow().is_empty(),
+            Object::Map(entries) => !entries.borrow().is_empty(),
+            Object::Print(v) => !v.is_empty(),
+            Object::Return(v) => v.is_truthy(),
+            Object::Class(_)
+            | Object::ClassInstance(_)
+            | Object::Function(_)
+            | Object::Break
+            | Object::Continue => true,
+        }
+    }
+
+    // big results that fit back into an i64 are demoted, so the same number is
+    // never both an Integer and a BigInt
+    pub fn from_bigint(v: BigInt) -> Object {
+        match v.to_i64() {
+            Some(n) => Object::Integer(n),
+            None => Object::BigInt(v),
+        }
+    }
+
+    pub fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Object::Integer(v) => Some(BigInt::from(*v)),
+            Object::BigInt(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    // the name of the object's type as a user would call it
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Class(_) => "class",
+            Object::ClassInstance(_) => "instance",
+            Object::Function(_) => "function",
+            Object::Integer(_) | Object::BigInt(_) => "integer",
+            Object::Float(_) => "float",
+            Object::Bool(_) => "bool",
+            Object::SString(_) => "string",
+            Object::Range(_, _) => "range",
+            Object::Tuple(_) => "tuple",
+            Object::List(_) => "list",
+            Object::Map(_) => "map",
+            Object::Return(v) => v.type_name(),
+            Object::Break | Object::Continue => "control flow",
+            Object::Print(_) => "print",
+            Object::Null => "null",
+        }
+    }
+
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            Object::Integer(v) => Some(*v as f64),
+            Object::BigInt(v) => v.to_f64(),
+            Object::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Object::Integer(v) => write!(f, "{}", v),
-            Object::Float(v) => write!(f, "{}", v),
-            Object::Number(v) => write!(f, "{}", v),
+            Object::BigInt(v) => write!(f, "{}", v),
+            // keep the fraction on whole floats so 2.0 doesn't read as the integer 2
+            Object::Float(v) => {
+                if v.is_finite() && v.fract() == 0.0 {
+                    write!(f, "{:.1}", v)
+                } else {
+                    write!(f, "{}", v)
+                }
+            }
             Object::SString(v) => write!(f, "{}", v),
             Object::Bool(v) => write!(f, "{}", v),
-            _ => write!(f, "{:?}", self),
+            Object::Range(start, end) => write!(f, "{}..{}", start, end),
+            Object::Function(func) => write!(f, "<fn {}>", func.name),
+            Object::Class(class) => write!(f, "<class {}>", class.name),
+            Object::ClassInstance(instance) => write!(f, "<{} instance>", instance.class.name),
+            Object::Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_item(f, item)?;
+                }
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Object::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_item(f, item)?;
+                }
+                write!(f, "]")
+            }
+            Object::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_item(f, &key.to_object())?;
+                    write!(f, ": ")?;
+                    fmt_item(f, value)?;
+                }
+                write!(f, "}}")
+            }
+            Object::Return(v) => write!(f, "{}", v),
+            Object::Print(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+            Object::Break => write!(f, "break"),
+            Object::Continue => write!(f, "continue"),
+            Object::Null => write!(f, "null"),
+        }
+    }
+}
+
+// strings are quoted inside collections so ("a, b",) reads unambiguously
+fn fmt_item(f: &mut fmt::Formatter<'_>, item: &Object) -> fmt::Result {
+    match item {
+        Object::SString(s) => write!(f, "{:?}", s),
+        _ => write!(f, "{}", item),
+    }
+}
+
+// the objects that can be map keys: the immutable ones without float equality problems
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MapKey {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    BigInt(BigInt),
+    SString(String),
+    Tuple(Vec<MapKey>),
+}
+
+impl MapKey {
+    pub fn from_object(obj: &Object) -> Option<MapKey> {
+        match obj {
+            Object::Null => Some(MapKey::Null),
+            Object::Bool(v) => Some(MapKey::Bool(*v)),
+            Object::Integer(v) => Some(MapKey::Integer(*v)),
+            Object::BigInt(v) => Some(MapKey::BigInt(v.clone())),
+            Object::SString(v) => Some(MapKey::SString(v.clone())),
+            Object::Tuple(items) => items
+                .iter()
+                .map(MapKey::from_object)
+                .collect::<Option<Vec<MapKey>>>()
+                .map(MapKey::Tuple),
+            _ => None,
+        }
+    }
+
+    pub fn to_object(&self) -> Object {
+        match self {
+            MapKey::Null => Object::Null,
+            MapKey::Bool(v) => Object::Bool(*v),
+            MapKey::Integer(v) => Object::Integer(*v),
+            MapKey::BigInt(v) => Object::BigInt(v.clone()),
+            MapKey::SString(v) => Object::SString(v.clone()),
+            MapKey::Tuple(items) => Object::Tuple(items.iter().map(MapKey::to_object).collect()),
+        }
+    }
+}
+
+// a closure: the function plus the scope it was defined in
+#[derive(Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Rc<Node>,
+    pub env: Rc<RefCell<Environment>>,
+}
+
+impl Function {
+    pub fn new(
+        name: String,
+        params: Vec<String>,
+        body: Rc<Node>,
+        env: Rc<RefCell<Environment>>,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            body,
+            env,
+        }
+    }
+}
+
+// the captured env usually holds the function itself, so don't walk into it
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Function {
+    // a copy of the method whose scope has `this` bound to the instance
+    pub fn bind(&self, instance: Object) -> Function {
+        let mut env = Environment::new_enclosed(self.env.clone());
+        env.define("this".to_string(), instance);
+        Function::new(
+            self.name.clone(),
+            self.params.clone(),
+            self.body.clone(),
+            Rc::new(RefCell::new(env)),
+        )
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.params == other.params
+            && Rc::ptr_eq(&self.body, &other.body)
+            && Rc::ptr_eq(&self.env, &other.env)
+    }
+}
+
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    pub methods: HashMap<String, Rc<Function>>,
+}
+
+impl Class {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<Class>>,
+        methods: HashMap<String, Rc<Function>>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    // own methods first, then up the superclass chain
+    pub fn find_method(&self, name: &str) -> Option<Rc<Function>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => match self.superclass {
+                Some(ref parent) => parent.find_method(name),
+                None => None,
+            },
         }
     }
 }
+
+impl fmt::Debug for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Class")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+// classes and instances compare by identity
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: RefCell<HashMap<String, Object>>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self {
+            class,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl fmt::Debug for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Instance")
+            .field("class", &self.class.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
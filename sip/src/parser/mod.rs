@@ -4,67 +4,348 @@ use crate::{
     ast::Program,
     ast::{self, Node},
     errors::ParserError,
-    tokens::{Token, TokenType},
+    span::Span,
+    tokens::{StrPart, Token, TokenType},
 };
 
 #[derive(Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
+    spans: Vec<Span>, // spans[i] is where tokens[i] came from, empty if unknown
     current: usize,
+    errors: Vec<ParserError>, // errors recovered from so far
+    nesting: usize,           // how many expression brackets the parser is inside
+    blocks: usize,            // how many { } blocks the parser is inside
 }
 
 impl Parser {
+    #[cfg(test)]
     pub fn new(tokens: Vec<Token>) -> Self {
+        Self::with_spans(tokens, vec![])
+    }
+
+    // nodes are only wrapped in Node::Spanned when the token spans are given
+    pub fn with_spans(tokens: Vec<Token>, spans: Vec<Span>) -> Self {
         Self {
-            tokens: tokens,
-            current: 0 as usize,
+            tokens,
+            spans,
+            current: 0,
+            errors: vec![],
+            nesting: 0,
+            blocks: 0,
         }
     }
 
+    #[cfg(test)]
     pub fn parse(&mut self) -> Result<Program, ParserError> {
+        let (program, mut errors) = self.parse_with_errors();
+        if errors.is_empty() {
+            return Ok(program);
+        }
+        Err(errors.remove(0))
+    }
+
+    // parse the whole input, skipping over statements that fail to parse. gives back
+    // what could be parsed together with every error in source order
+    pub fn parse_with_errors(&mut self) -> (Program, Vec<ParserError>) {
         let mut nodes: Vec<Node> = vec![];
         loop {
             if self.is_at_end() {
                 break;
             }
+            if self.match_tk(TokenType::Semicolon) {
+                continue;
+            }
 
-            let node_res = self.declare();
-            match node_res {
-                Ok(v) => {
-                    nodes.push(v);
-                }
-                Err(e) => {
-                    return Err(e);
-                }
+            if let Some(node) = self.declare_or_recover() {
+                nodes.push(node);
+            }
+        }
+
+        (Program::new(nodes), std::mem::take(&mut self.errors))
+    }
+
+    // a statement that fails to parse is recorded and dropped
+    fn declare_or_recover(&mut self) -> Option<Node> {
+        let start = self.current;
+        match self.declare() {
+            Ok(node) => Some(node),
+            Err(e) => {
+                self.errors.push(e);
+                self.synchronize(start);
+                None
+            }
+        }
+    }
+
+    // skip to where the next statement probably starts: after a ;, before a } or a
+    // statement keyword, or at the start of a new line. a { the failed statement
+    // opened is skipped up to its matching }, so the rest of the block is not parsed
+    // as statements of their own
+    fn synchronize(&mut self, start: usize) {
+        // the statement failed on its first token, which can not start a statement
+        if self.current == start {
+            self.advance();
+        }
+        // how many { the failed statement left open
+        let mut depth: usize = 0;
+        for tk in &self.tokens[start..self.current] {
+            match tk.token_type() {
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace => depth = depth.saturating_sub(1),
+                _ => {}
             }
         }
 
-        Ok(Program::new(nodes))
+        while !self.is_at_end() {
+            if depth > 0 {
+                match self.advance().token_type() {
+                    TokenType::LBrace => depth += 1,
+                    TokenType::RBrace => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return;
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            if self.previous() == Token::Semicolon {
+                return;
+            }
+
+            if self.starts_line() {
+                return;
+            }
+
+            match self.peek().token_type() {
+                // outside any block a } is part of the failed statement, not the end
+                // of a block. skip it instead of reporting it as a statement again
+                TokenType::RBrace if self.blocks == 0 => {}
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace
+                | TokenType::Var
+                | TokenType::Class
+                | TokenType::Def
+                | TokenType::If
+                | TokenType::For
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue => return,
+                _ => {}
+            }
+            self.advance();
+        }
     }
 
     fn declare(&mut self) -> Result<Node, ParserError> {
+        let start = self.peek_span();
+        let node = self.parse_declaration()?;
+        let node = self.spanned(start.to(&self.previous_span()), node);
+        self.end_statement()?;
+        Ok(node)
+    }
+
+    // a statement ends at a ;, a line break, a } or the end of the input. one that
+    // ends with a } of its own, like a function or an if, can be followed by another
+    // on the same line
+    fn end_statement(&mut self) -> Result<(), ParserError> {
+        if self.match_tk(TokenType::Semicolon) {
+            return Ok(());
+        }
+        if self.is_at_end()
+            || self.check(TokenType::RBrace)
+            || self.previous().token_type() == TokenType::RBrace
+            || self.starts_line()
+        {
+            return Ok(());
+        }
+        // without spans there is no telling where the lines break, and next to what
+        // the lexer could not lex the error is already reported
+        if !self.peek_span().is_known()
+            || self.peek() == Token::Unkown
+            || self.previous() == Token::Unkown
+        {
+            return Ok(());
+        }
+
+        Err(ParserError::ExpectedTokenNotFound(
+            "expect ; or a new line after statement".to_string(),
+            self.peek_span(),
+        ))
+    }
+
+    fn parse_declaration(&mut self) -> Result<Node, ParserError> {
         if self.match_tk(TokenType::Var) {
             return self.parse_var();
         }
 
+        if self.match_tk(TokenType::Class) {
+            return self.parse_class();
+        }
+
+        // def name(...) declares a function, an anonymous def (...) falls through to primary
+        if self.check(TokenType::Def) && self.check_next(TokenType::Ident) {
+            self.advance();
+            return self.parse_func();
+        }
+
         self.parse_stmt()
     }
 
     fn parse_var(&mut self) -> Result<Node, ParserError> {
+        if self.match_tk(TokenType::LParent) {
+            return self.parse_destructure();
+        }
+
         let ident = self.consume(TokenType::Ident, "expect ident token".to_string())?;
 
         let mut init_expr = Node::Null;
-        if self.match_tk(TokenType::Assign) {
+        if self.match_infix(vec![TokenType::Assign]) {
             init_expr = self.parse_expr()?;
         }
 
         Ok(Node::VarStmt(ast::VarStmt::new(ident, init_expr)))
     }
 
+    // var (a, b) = value
+    fn parse_destructure(&mut self) -> Result<Node, ParserError> {
+        let mut names: Vec<Token> = vec![];
+        loop {
+            names.push(self.consume(TokenType::Ident, "expect ident token".to_string())?);
+            if !self.match_tk(TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::RParent, "expect ) after var names".to_string())?;
+        self.consume(TokenType::Assign, "expect = after var names".to_string())?;
+
+        let value = self.parse_expr()?;
+        Ok(Node::Destructure(ast::Destructure::new(names, value)))
+    }
+
+    fn parse_func(&mut self) -> Result<Node, ParserError> {
+        let name = self.consume(
+            TokenType::Ident,
+            "expect function name after def".to_string(),
+        )?;
+        self.consume(
+            TokenType::LParent,
+            "expect ( after function name".to_string(),
+        )?;
+        let params = self.parse_params()?;
+
+        self.consume(
+            TokenType::LBrace,
+            "expect { before function body".to_string(),
+        )?;
+        let body = self.parse_block()?;
+
+        Ok(Node::FuncDecl(ast::FuncDecl::new(name, params, body)))
+    }
+
+    fn parse_class(&mut self) -> Result<Node, ParserError> {
+        let name = self.consume(TokenType::Ident, "expect class name".to_string())?;
+
+        let mut superclass = Node::Null;
+        if self.match_tk(TokenType::Lt) {
+            let parent = self.consume(TokenType::Ident, "expect superclass name".to_string())?;
+            superclass = self.spanned(self.previous_span(), Node::Identifier(parent));
+        }
+
+        self.consume(TokenType::LBrace, "expect { before class body".to_string())?;
+
+        let mut methods: Vec<ast::FuncDecl> = vec![];
+        while !self.is_at_end() && !self.check(TokenType::RBrace) {
+            self.consume(TokenType::Def, "expect def in class body".to_string())?;
+            if let Node::FuncDecl(method) = self.parse_func()? {
+                methods.push(method);
+            }
+        }
+        self.consume(TokenType::RBrace, "expect } after class body".to_string())?;
+
+        Ok(Node::ClassDecl(ast::ClassDecl::new(
+            name, superclass, methods,
+        )))
+    }
+
+    fn parse_params(&mut self) -> Result<Vec<Token>, ParserError> {
+        let mut params: Vec<Token> = vec![];
+        if !self.check(TokenType::RParent) {
+            loop {
+                let param = self.consume(TokenType::Ident, "expect parameter name".to_string())?;
+                params.push(param);
+                if !self.match_tk(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RParent, "expect ) after parameters".to_string())?;
+
+        Ok(params)
+    }
+
+    // def (a, b) { ... }
+    fn parse_lambda(&mut self) -> Result<Node, ParserError> {
+        self.consume(TokenType::LParent, "expect ( after def".to_string())?;
+        let params = self.parse_params()?;
+
+        self.consume(
+            TokenType::LBrace,
+            "expect { before function body".to_string(),
+        )?;
+        let body = self.parse_block()?;
+
+        Ok(Node::Lambda(ast::Lambda::new(params, body)))
+    }
+
+    // the part after =>, a bare expression body returns its value
+    fn parse_arrow_body(&mut self, params: Vec<Token>) -> Result<Node, ParserError> {
+        if self.match_tk(TokenType::LBrace) {
+            let body = self.parse_block()?;
+            return Ok(Node::Lambda(ast::Lambda::new(params, body)));
+        }
+
+        let exp = self.parse_expr()?;
+        let body = Node::Block(vec![Node::Return(ast::Return::new(exp))]);
+        Ok(Node::Lambda(ast::Lambda::new(params, body)))
+    }
+
+    // turn the already parsed (a, b) in front of => into parameter names, the => has
+    // just been matched
+    fn arrow_params(&self, exp: Node) -> Result<Vec<Token>, ParserError> {
+        let items = match exp {
+            Node::Group(inner) => vec![(*inner).clone()],
+            Node::Tuple(items) => items,
+            _ => vec![exp],
+        };
+
+        let mut params: Vec<Token> = vec![];
+        for item in items {
+            match item.unspanned() {
+                Node::Identifier(tk) => params.push(tk.clone()),
+                _ => {
+                    return Err(ParserError::ExpectedTokenNotFound(
+                        "expect parameter names before =>".to_string(),
+                        self.previous_span(),
+                    ))
+                }
+            }
+        }
+        Ok(params)
+    }
+
     fn parse_stmt(&mut self) -> Result<Node, ParserError> {
         if self.match_tk(TokenType::If) {
             return self.parse_if();
         }
+        if self.match_tk(TokenType::Print) {
+            return self.nested(Self::parse_print);
+        }
         if self.match_tk(TokenType::For) {
             return self.parse_for();
         }
@@ -73,6 +354,14 @@ impl Parser {
             return self.parse_while();
         }
 
+        if self.match_tk(TokenType::Break) {
+            return Ok(Node::Break);
+        }
+
+        if self.match_tk(TokenType::Continue) {
+            return Ok(Node::Continue);
+        }
+
         if self.match_tk(TokenType::Return) {
             return self.parse_return();
         }
@@ -92,7 +381,7 @@ impl Parser {
     fn parse_if(&mut self) -> Result<Node, ParserError> {
         self.consume(TokenType::LParent, "expect ( after if".to_string())?;
 
-        let cond = self.parse_expr()?;
+        let cond = self.nested(Self::parse_expr)?;
         self.consume(TokenType::RParent, "expect ) after if".to_string())?;
 
         let then = self.parse_stmt()?;
@@ -110,27 +399,126 @@ impl Parser {
     }
 
     fn parse_for(&mut self) -> Result<Node, ParserError> {
-        Ok(Node::Null)
+        if !self.match_tk(TokenType::LParent) {
+            return self.parse_for_in();
+        }
+
+        let (init, cond, step) = self.nested(Self::parse_for_header)?;
+        let body = self.parse_stmt()?;
+        Ok(Node::For(ast::For::new(init, cond, step, body)))
+    }
+
+    // init; cond; step) of a for loop
+    fn parse_for_header(&mut self) -> Result<(Node, Node, Node), ParserError> {
+        let mut init = Node::Null;
+        if self.match_tk(TokenType::Var) {
+            init = self.parse_var()?;
+        } else if !self.check(TokenType::Semicolon) {
+            init = self.parse_expr()?;
+        }
+        self.consume(TokenType::Semicolon, "expect ; after for init".to_string())?;
+
+        let mut cond = Node::Null;
+        if !self.check(TokenType::Semicolon) {
+            cond = self.parse_expr()?;
+        }
+        self.consume(TokenType::Semicolon, "expect ; after for cond".to_string())?;
+
+        let mut step = Node::Null;
+        if !self.check(TokenType::RParent) {
+            step = self.parse_expr()?;
+        }
+        self.consume(TokenType::RParent, "expect ) after for".to_string())?;
+
+        Ok((init, cond, step))
+    }
+
+    fn parse_for_in(&mut self) -> Result<Node, ParserError> {
+        let name = self.consume(TokenType::Ident, "expect ident after for".to_string())?;
+        self.consume(TokenType::In, "expect in after for ident".to_string())?;
+
+        let iterable = self.parse_expr()?;
+        let body = self.parse_stmt()?;
+        Ok(Node::ForIn(ast::ForIn::new(name, iterable, body)))
     }
 
     fn parse_while(&mut self) -> Result<Node, ParserError> {
-        Ok(Node::Null)
+        self.consume(TokenType::LParent, "expect ( after while".to_string())?;
+
+        let cond = self.nested(Self::parse_expr)?;
+        self.consume(TokenType::RParent, "expect ) after while".to_string())?;
+
+        let body = self.parse_stmt()?;
+        Ok(Node::While(ast::While::new(cond, body)))
+    }
+
+    fn parse_print(&mut self) -> Result<Node, ParserError> {
+        self.consume(TokenType::LParent, "expect ( after print".to_string())?;
+
+        let mut args: Vec<Node> = vec![];
+        if !self.check(TokenType::RParent) {
+            loop {
+                args.push(self.parse_expr()?);
+                if !self.match_tk(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RParent, "expect ) after print".to_string())?;
+
+        Ok(Node::Print(args))
     }
 
     fn parse_return(&mut self) -> Result<Node, ParserError> {
+        // a bare return at the end of a block or its line returns null
+        if self.is_at_end()
+            || self.check(TokenType::RBrace)
+            || self.check(TokenType::Semicolon)
+            || self.starts_line()
+        {
+            return Ok(Node::Return(ast::Return::new(Node::Null)));
+        }
+
         let ret_val = self.parse_expr()?;
+        // return a, b gives back a tuple
+        if self.match_tk(TokenType::Comma) {
+            let mut values = vec![ret_val];
+            loop {
+                values.push(self.parse_expr()?);
+                if !self.match_tk(TokenType::Comma) {
+                    break;
+                }
+            }
+            return Ok(Node::Return(ast::Return::new(Node::Tuple(values))));
+        }
+
         Ok(Node::Return(ast::Return::new(ret_val)))
     }
 
+    // a block keeps its own statements apart by line even inside brackets, e.g. in a
+    // function passed as an argument
     fn parse_block(&mut self) -> Result<Node, ParserError> {
+        let nesting = std::mem::take(&mut self.nesting);
+        self.blocks += 1;
+        let res = self.parse_block_stmts();
+        self.blocks -= 1;
+        self.nesting = nesting;
+        res
+    }
+
+    fn parse_block_stmts(&mut self) -> Result<Node, ParserError> {
         let mut stmts: Vec<Node> = vec![];
         loop {
             if self.is_at_end() || self.check(TokenType::RBrace) {
                 break;
             }
+            if self.match_tk(TokenType::Semicolon) {
+                continue;
+            }
 
-            let stmt = self.declare()?;
-            stmts.push(stmt);
+            if let Some(stmt) = self.declare_or_recover() {
+                stmts.push(stmt);
+            }
         }
 
         self.consume(TokenType::RBrace, "expect } after block".to_string())?;
@@ -138,20 +526,51 @@ impl Parser {
         Ok(Node::Block(stmts))
     }
 
-    fn parse_expr_stmt(&mut self) -> Result<Node, ParserError> {
-        let exp = self.parse_expr()?;
-        Ok(Node::ExpressionStmt(Rc::new(exp)))
-    }
-
     fn assignment(&mut self) -> Result<Node, ParserError> {
         let exp = self.or()?;
-        if self.match_tk(TokenType::Assign) {
+        if self.match_infix(vec![TokenType::Assign]) {
+            let span = self.previous_span();
             let value = self.assignment()?;
-            let res = match exp {
+            let res = match exp.unspanned().clone() {
                 Node::Identifier(ident) => Ok(Node::Assign(ast::Assign::new(ident, value))),
-                _ => Err(ParserError::NotSupportedToken(Token::Unkown)),
+                Node::Get(get) => Ok(Node::Set(ast::Set::new(
+                    (*get.object).clone(),
+                    get.name,
+                    value,
+                ))),
+                Node::Index(index) => Ok(Node::SetIndex(ast::SetIndex::new(
+                    (*index.value).clone(),
+                    (*index.index).clone(),
+                    value,
+                ))),
+                target => Err(ParserError::InvalidAssignTarget(
+                    Box::new(target),
+                    span.clone(),
+                )),
+            };
+            return res.map(|node| self.spanned(span, node));
+        }
+
+        if self.match_infix(vec![
+            TokenType::PlusAssign,
+            TokenType::MinusAssign,
+            TokenType::StarAssign,
+            TokenType::SlashAssign,
+            TokenType::PercentAssign,
+        ]) {
+            let op = self.previous();
+            let span = self.previous_span();
+            let value = self.assignment()?;
+            return match exp.unspanned() {
+                Node::Identifier(_) | Node::Get(_) | Node::Index(_) => Ok(self.spanned(
+                    span,
+                    Node::CompoundAssign(ast::CompoundAssign::new(exp, op, value)),
+                )),
+                target => Err(ParserError::InvalidAssignTarget(
+                    Box::new(target.clone()),
+                    span,
+                )),
             };
-            return res;
         }
 
         Ok(exp)
@@ -160,11 +579,12 @@ impl Parser {
     fn or(&mut self) -> Result<Node, ParserError> {
         let mut res = self.and()?;
         loop {
-            if self.match_tk(TokenType::Or) {
+            if self.match_infix(vec![TokenType::Or]) {
                 let op = self.previous();
+                let span = self.previous_span();
                 let exp = self.and()?;
 
-                res = Node::Logical(Rc::new(res), op, Rc::new(exp));
+                res = self.spanned(span, Node::Logical(Rc::new(res), op, Rc::new(exp)));
                 continue;
             }
             break;
@@ -176,10 +596,11 @@ impl Parser {
     fn and(&mut self) -> Result<Node, ParserError> {
         let mut exp = self.equality()?;
         loop {
-            if self.match_tk(TokenType::And) {
+            if self.match_infix(vec![TokenType::And]) {
                 let op = self.previous();
+                let span = self.previous_span();
                 let r_exp = self.equality()?;
-                exp = Node::Logical(Rc::new(exp), op, Rc::new(r_exp));
+                exp = self.spanned(span, Node::Logical(Rc::new(exp), op, Rc::new(r_exp)));
                 continue;
             }
 
@@ -191,10 +612,11 @@ impl Parser {
     fn equality(&mut self) -> Result<Node, ParserError> {
         let mut exp = self.comparison()?;
         loop {
-            if self.match_tks(vec![TokenType::EQ, TokenType::NotEQ]) {
+            if self.match_infix(vec![TokenType::EQ, TokenType::NotEQ]) {
                 let op = self.previous();
+                let span = self.previous_span();
                 let r_exp = self.comparison()?;
-                exp = Node::Binary(Rc::new(exp), op, Rc::new(r_exp));
+                exp = self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp)));
                 continue;
             }
 
@@ -204,9 +626,9 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> Result<Node, ParserError> {
-        let mut exp = self.term()?;
+        let mut exp = self.range()?;
         loop {
-            if self.match_tks(vec![
+            if self.match_infix(vec![
                 TokenType::Lt,
                 TokenType::LtEQ,
                 TokenType::Gt,
@@ -215,8 +637,9 @@ impl Parser {
                 TokenType::NotEQ,
             ]) {
                 let op = self.previous();
-                let r_exp = self.term()?;
-                exp = Node::Binary(Rc::new(exp), op, Rc::new(r_exp));
+                let span = self.previous_span();
+                let r_exp = self.range()?;
+                exp = self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp)));
                 continue;
             }
             break;
@@ -224,13 +647,75 @@ impl Parser {
         Ok(exp)
     }
 
+    // start..end
+    fn range(&mut self) -> Result<Node, ParserError> {
+        let exp = self.bit_or()?;
+        if self.match_infix(vec![TokenType::DotDot]) {
+            let op = self.previous();
+            let span = self.previous_span();
+            let r_exp = self.bit_or()?;
+            return Ok(self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp))));
+        }
+
+        Ok(exp)
+    }
+
+    fn bit_or(&mut self) -> Result<Node, ParserError> {
+        let mut exp = self.bit_xor()?;
+        while self.match_infix(vec![TokenType::BitOr]) {
+            let op = self.previous();
+            let span = self.previous_span();
+            let r_exp = self.bit_xor()?;
+            exp = self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp)));
+        }
+
+        Ok(exp)
+    }
+
+    fn bit_xor(&mut self) -> Result<Node, ParserError> {
+        let mut exp = self.bit_and()?;
+        while self.match_infix(vec![TokenType::BitXor]) {
+            let op = self.previous();
+            let span = self.previous_span();
+            let r_exp = self.bit_and()?;
+            exp = self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp)));
+        }
+
+        Ok(exp)
+    }
+
+    fn bit_and(&mut self) -> Result<Node, ParserError> {
+        let mut exp = self.shift()?;
+        while self.match_infix(vec![TokenType::BitAnd]) {
+            let op = self.previous();
+            let span = self.previous_span();
+            let r_exp = self.shift()?;
+            exp = self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp)));
+        }
+
+        Ok(exp)
+    }
+
+    fn shift(&mut self) -> Result<Node, ParserError> {
+        let mut exp = self.term()?;
+        while self.match_infix(vec![TokenType::ShiftLeft, TokenType::ShiftRight]) {
+            let op = self.previous();
+            let span = self.previous_span();
+            let r_exp = self.term()?;
+            exp = self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp)));
+        }
+
+        Ok(exp)
+    }
+
     fn term(&mut self) -> Result<Node, ParserError> {
         let mut exp = self.factor()?;
         loop {
-            if self.match_tks(vec![TokenType::Plus, TokenType::Minus]) {
+            if self.match_infix(vec![TokenType::Plus, TokenType::Minus]) {
                 let op = self.previous();
+                let span = self.previous_span();
                 let r_exp = self.factor()?;
-                exp = Node::Binary(Rc::new(exp), op, Rc::new(r_exp));
+                exp = self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp)));
                 continue;
             }
 
@@ -243,10 +728,16 @@ impl Parser {
     fn factor(&mut self) -> Result<Node, ParserError> {
         let mut exp = self.unary()?;
         loop {
-            if self.match_tks(vec![TokenType::Slash, TokenType::Star]) {
+            if self.match_infix(vec![
+                TokenType::Slash,
+                TokenType::TildeSlash,
+                TokenType::Star,
+                TokenType::Percent,
+            ]) {
                 let op = self.previous();
+                let span = self.previous_span();
                 let r_exp = self.unary()?;
-                exp = Node::Binary(Rc::new(exp), op, Rc::new(r_exp));
+                exp = self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp)));
                 continue;
             }
 
@@ -257,37 +748,265 @@ impl Parser {
     }
 
     fn unary(&mut self) -> Result<Node, ParserError> {
-        if self.match_tks(vec![TokenType::Minus, TokenType::Bang]) {
+        if self.match_tks(vec![TokenType::Minus, TokenType::Bang, TokenType::BitNot]) {
             let op = self.previous();
+            let span = self.previous_span();
             let val = self.unary()?;
-            return Ok(Node::Unary(ast::Unary::new(op, val)));
+            return Ok(self.spanned(span, Node::Unary(ast::Unary::new(op, val))));
+        }
+        self.power()
+    }
+
+    // ** binds tighter than a unary on its left and is right associative:
+    // -2 ** 2 is -(2 ** 2) and 2 ** 3 ** 2 is 2 ** (3 ** 2)
+    fn power(&mut self) -> Result<Node, ParserError> {
+        let exp = self.call()?;
+        if self.match_infix(vec![TokenType::DoubleStar]) {
+            let op = self.previous();
+            let span = self.previous_span();
+            let r_exp = self.unary()?;
+            return Ok(self.spanned(span, Node::Binary(Rc::new(exp), op, Rc::new(r_exp))));
+        }
+
+        Ok(exp)
+    }
+
+    // calls, property and index access span from the start of the callee or object
+    fn call(&mut self) -> Result<Node, ParserError> {
+        let start = self.peek_span();
+        let mut exp = self.primary()?;
+        loop {
+            // a ( or [ starting a line begins a new statement, a . continues a chain
+            if self.match_infix(vec![TokenType::LParent]) {
+                exp = self.nested(|p| p.finish_call(exp))?;
+                exp = self.spanned(start.to(&self.previous_span()), exp);
+                continue;
+            }
+
+            if self.match_tk(TokenType::Dot) {
+                let name =
+                    self.consume(TokenType::Ident, "expect property name after .".to_string())?;
+                exp = Node::Get(ast::Get::new(exp, name));
+                exp = self.spanned(start.to(&self.previous_span()), exp);
+                continue;
+            }
+
+            if self.match_infix(vec![TokenType::LSBracket]) {
+                let index = self.nested(|p| {
+                    let index = p.parse_expr()?;
+                    p.consume(TokenType::RSBracket, "expect ] after index".to_string())?;
+                    Ok(index)
+                })?;
+                exp = Node::Index(ast::Index::new(exp, index));
+                exp = self.spanned(start.to(&self.previous_span()), exp);
+                continue;
+            }
+
+            break;
+        }
+
+        Ok(exp)
+    }
+
+    fn finish_call(&mut self, callee: Node) -> Result<Node, ParserError> {
+        let mut args: Vec<Node> = vec![];
+        if !self.check(TokenType::RParent) {
+            loop {
+                args.push(self.parse_expr()?);
+                if !self.match_tk(TokenType::Comma) {
+                    break;
+                }
+            }
         }
-        self.primary()
+        self.consume(TokenType::RParent, "expect ) after arguments".to_string())?;
+
+        Ok(Node::Call(ast::Call::new(callee, args)))
     }
 
     fn primary(&mut self) -> Result<Node, ParserError> {
+        // the lexer has already reported what it could not lex, stand in for it so the
+        // rest is still parsed
+        if self.peek() == Token::Unkown {
+            self.advance();
+            return Ok(Node::Null);
+        }
+
         if self.match_tk(TokenType::True)
             || self.match_tk(TokenType::False)
             || self.match_tk(TokenType::Integer)
+            || self.match_tk(TokenType::BigInteger)
             || self.match_tk(TokenType::Float)
             || self.match_tk(TokenType::String)
-            || self.match_tk(TokenType::Null)
         {
             return Ok(Node::Literal(self.previous()));
         } else if self.match_tk(TokenType::Ident) {
-            return Ok(Node::Identifier(self.previous()));
+            let ident = self.previous();
+            if self.match_infix(vec![TokenType::Arrow]) {
+                return self.parse_arrow_body(vec![ident]);
+            }
+            return Ok(self.spanned(self.previous_span(), Node::Identifier(ident)));
         } else if self.match_tk(TokenType::LParent) {
-            let exp = self.parse_expr()?;
+            let exp = self.nested(Self::group_or_tuple)?;
+            if self.match_infix(vec![TokenType::Arrow]) {
+                let params = self.arrow_params(exp)?;
+                return self.parse_arrow_body(params);
+            }
+            return Ok(exp);
+        } else if self.match_tk(TokenType::Interpolated) {
+            return self.parse_interpolation();
+        } else if self.match_tk(TokenType::LSBracket) {
+            let start = self.previous_span();
+            let list = self.nested(Self::parse_list)?;
+            return Ok(self.spanned(start.to(&self.previous_span()), list));
+        } else if self.match_tk(TokenType::LBrace) {
+            let start = self.previous_span();
+            let map = self.nested(Self::parse_map)?;
+            return Ok(self.spanned(start.to(&self.previous_span()), map));
+        } else if self.match_tk(TokenType::Def) {
+            return self.parse_lambda();
+        } else if self.match_tk(TokenType::This) {
+            return Ok(self.spanned(self.previous_span(), Node::This));
+        } else if self.match_tk(TokenType::Super) {
+            let start = self.previous_span();
+            self.consume(TokenType::Dot, "expect . after super".to_string())?;
+            let method = self.consume(
+                TokenType::Ident,
+                "expect superclass method name".to_string(),
+            )?;
+            return Ok(self.spanned(start.to(&self.previous_span()), Node::Super(method)));
+        }
+
+        Err(ParserError::NotSupportedToken(
+            self.peek(),
+            self.peek_span(),
+        ))
+    }
+
+    // (a) is a group, (), (a,) and (a, b) are tuples
+    fn group_or_tuple(&mut self) -> Result<Node, ParserError> {
+        if self.match_tk(TokenType::RParent) {
+            return Ok(Node::Tuple(vec![]));
+        }
+
+        let exp = self.parse_expr()?;
+        if !self.match_tk(TokenType::Comma) {
+            self.consume(TokenType::RParent, "expect ) after expression".to_string())?;
             return Ok(Node::Group(Rc::new(exp)));
         }
 
-        Err(ParserError::NotSupportedToken(self.peek()))
+        let mut items = vec![exp];
+        while !self.check(TokenType::RParent) {
+            items.push(self.parse_expr()?);
+            if !self.match_tk(TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::RParent, "expect ) after tuple".to_string())?;
+
+        Ok(Node::Tuple(items))
+    }
+
+    // every ${...} part was lexed into its own token list, parse each one as a
+    // complete expression. the parts have no spans of their own, they get the span of
+    // the whole string
+    fn parse_interpolation(&mut self) -> Result<Node, ParserError> {
+        let span = self.previous_span();
+        let parts = match self.previous() {
+            Token::Interpolated(parts) => parts,
+            tk => return Err(ParserError::NotSupportedToken(tk, span)),
+        };
+
+        let mut nodes: Vec<Node> = vec![];
+        for part in parts {
+            match part {
+                StrPart::Text(text) => nodes.push(Node::Literal(Token::SString(text))),
+                StrPart::Expr(tokens) => {
+                    let spans = if span.is_known() {
+                        vec![span.clone(); tokens.len()]
+                    } else {
+                        vec![]
+                    };
+                    let mut parser = Parser::with_spans(tokens, spans);
+                    let exp = parser.parse_expr()?;
+                    if !parser.is_at_end() {
+                        return Err(ParserError::NotSupportedToken(
+                            parser.peek(),
+                            parser.peek_span(),
+                        ));
+                    }
+                    nodes.push(exp);
+                }
+            }
+        }
+
+        Ok(self.spanned(span, Node::Interpolation(nodes)))
+    }
+
+    // [a, b, c], a trailing comma is allowed
+    fn parse_list(&mut self) -> Result<Node, ParserError> {
+        let mut items = vec![];
+        while !self.check(TokenType::RSBracket) {
+            items.push(self.parse_expr()?);
+            if !self.match_tk(TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::RSBracket, "expect ] after list".to_string())?;
+
+        Ok(Node::List(items))
+    }
+
+    // {key: value, ...}, only reached in expression position, a { that starts a
+    // statement is still a block
+    fn parse_map(&mut self) -> Result<Node, ParserError> {
+        let mut entries = vec![];
+        while !self.check(TokenType::RBrace) {
+            let key = self.parse_expr()?;
+            self.consume(TokenType::Colon, "expect : after map key".to_string())?;
+            let value = self.parse_expr()?;
+            entries.push((key, value));
+            if !self.match_tk(TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::RBrace, "expect } after map".to_string())?;
+
+        Ok(Node::Map(entries))
     }
 
     fn match_tk(&mut self, tk_type: TokenType) -> bool {
         self.match_tks(vec![tk_type])
     }
 
+    // for tokens that continue an expression: outside of brackets they have to be on
+    // the same line as what they continue, so `x = 1` and `-2` on the next line are
+    // two statements while `x = 1 -` and `2` are one
+    fn match_infix(&mut self, tk_types: Vec<TokenType>) -> bool {
+        if self.nesting == 0 && self.starts_line() {
+            return false;
+        }
+        self.match_tks(tk_types)
+    }
+
+    // whether the next token is on a later line than the previous one, never true
+    // without spans
+    fn starts_line(&self) -> bool {
+        let previous = self.previous_span();
+        let next = self.peek_span();
+        previous.is_known() && next.is_known() && next.line > previous.end_line
+    }
+
+    // line breaks don't end anything inside the (), [] and {} of an expression
+    fn nested<T, F>(&mut self, f: F) -> Result<T, ParserError>
+    where
+        F: FnOnce(&mut Self) -> Result<T, ParserError>,
+    {
+        self.nesting += 1;
+        let res = f(self);
+        self.nesting -= 1;
+        res
+    }
+
     fn match_tks(&mut self, tk_types: Vec<TokenType>) -> bool {
         for tk_tp in tk_types {
             if self.check(tk_tp) {
@@ -303,8 +1022,16 @@ impl Parser {
         if self.check(tk_type) {
             return Ok(self.advance());
         }
+        // what the lexer could not lex is already reported, and may have swallowed the
+        // expected token, so it stands in for that token instead of a second error
+        if self.peek() == Token::Unkown {
+            return Ok(self.advance());
+        }
+        if self.previous() == Token::Unkown {
+            return Ok(Token::Unkown);
+        }
 
-        Err(ParserError::ExpectedTokenNotFound(msg))
+        Err(ParserError::ExpectedTokenNotFound(msg, self.peek_span()))
     }
 
     fn check(&mut self, tk_type: TokenType) -> bool {
@@ -315,6 +1042,13 @@ impl Parser {
         self.peek().token_type() == tk_type
     }
 
+    fn check_next(&self, tk_type: TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(tk) => tk.token_type() == tk_type,
+            None => false,
+        }
+    }
+
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1;
@@ -333,6 +1067,25 @@ impl Parser {
     fn peek(&self) -> Token {
         self.tokens[self.current].clone()
     }
+
+    fn previous_span(&self) -> Span {
+        match self.current {
+            0 => Span::default(),
+            i => self.spans.get(i - 1).cloned().unwrap_or_default(),
+        }
+    }
+
+    fn peek_span(&self) -> Span {
+        self.spans.get(self.current).cloned().unwrap_or_default()
+    }
+
+    // wrap node with where it came from, nodes from tokens without spans stay bare
+    fn spanned(&self, span: Span, node: Node) -> Node {
+        if !span.is_known() {
+            return node;
+        }
+        Node::Spanned(span, Rc::new(node))
+    }
 }
 
 #[cfg(test)]
@@ -341,6 +1094,7 @@ mod tests {
     use crate::{
         ast::Node,
         ast::{self, Program},
+        lexer::Lexer,
         tokens::Token,
     };
     use std::rc::Rc;
@@ -356,7 +1110,7 @@ mod tests {
         ]);
         let res = parser.parse();
         println!("parse result: {:?}", res);
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         assert_eq!(
             Program::new(vec![Node::VarStmt(ast::VarStmt::new(
                 Token::Ident("x".to_string()),
@@ -378,7 +1132,7 @@ mod tests {
         ]);
         let res = parser.parse();
         println!("parse result: {:?}", res);
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         assert_eq!(
             Program::new(vec![
                 Node::Literal(Token::Integer(100)),
@@ -403,7 +1157,7 @@ mod tests {
         ]);
         let res = parser.parse();
         println!("parse result: {:?}", res);
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         assert_eq!(
             Program::new(vec![Node::Binary(
                 Rc::new(Node::Literal(Token::Integer(100))),
@@ -432,7 +1186,7 @@ mod tests {
         ]);
         let res = parser.parse();
         println!("parse result: {:?}", res);
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         assert_eq!(
             Program::new(vec![Node::Logical(
                 Rc::new(Node::Binary(
@@ -467,7 +1221,7 @@ mod tests {
         ]);
         let res = parser.parse();
         println!("parse result: {:?}", res);
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         assert_eq!(
             Program::new(vec![Node::IfStmt(
                 Rc::new(Node::Binary(
@@ -483,4 +1237,786 @@ mod tests {
             res.unwrap()
         );
     }
+
+    #[test]
+    fn test_parse_while() {
+        let mut parser = Parser::new(vec![
+            Token::While,
+            Token::LParent('('),
+            Token::True,
+            Token::RParent(')'),
+            Token::LBrace('{'),
+            Token::Continue,
+            Token::Break,
+            Token::RBrace('}'),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            Program::new(vec![Node::While(ast::While::new(
+                Node::Literal(Token::True),
+                Node::Block(vec![Node::Continue, Node::Break]),
+            ))]),
+            res.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_for() {
+        let mut parser = Parser::new(vec![
+            Token::For,
+            Token::LParent('('),
+            Token::Var,
+            Token::Ident("i".to_string()),
+            Token::Assign('='),
+            Token::Integer(0),
+            Token::Semicolon,
+            Token::Ident("i".to_string()),
+            Token::Lt("<".to_string()),
+            Token::Integer(3),
+            Token::Semicolon,
+            Token::RParent(')'),
+            Token::LBrace('{'),
+            Token::RBrace('}'),
+            Token::For,
+            Token::Ident("x".to_string()),
+            Token::In,
+            Token::Integer(0),
+            Token::DotDot,
+            Token::Integer(3),
+            Token::LBrace('{'),
+            Token::RBrace('}'),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            Program::new(vec![
+                Node::For(ast::For::new(
+                    Node::VarStmt(ast::VarStmt::new(
+                        Token::Ident("i".to_string()),
+                        Node::Literal(Token::Integer(0))
+                    )),
+                    Node::Binary(
+                        Rc::new(Node::Identifier(Token::Ident("i".to_string()))),
+                        Token::Lt("<".to_string()),
+                        Rc::new(Node::Literal(Token::Integer(3))),
+                    ),
+                    Node::Null,
+                    Node::Block(vec![]),
+                )),
+                Node::ForIn(ast::ForIn::new(
+                    Token::Ident("x".to_string()),
+                    Node::Binary(
+                        Rc::new(Node::Literal(Token::Integer(0))),
+                        Token::DotDot,
+                        Rc::new(Node::Literal(Token::Integer(3))),
+                    ),
+                    Node::Block(vec![]),
+                )),
+            ]),
+            res.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_func() {
+        let mut parser = Parser::new(vec![
+            Token::Def,
+            Token::Ident("add".to_string()),
+            Token::LParent('('),
+            Token::Ident("a".to_string()),
+            Token::Comma,
+            Token::Ident("b".to_string()),
+            Token::RParent(')'),
+            Token::LBrace('{'),
+            Token::Return,
+            Token::Ident("a".to_string()),
+            Token::RBrace('}'),
+            Token::Ident("add".to_string()),
+            Token::LParent('('),
+            Token::Integer(1),
+            Token::Comma,
+            Token::Integer(2),
+            Token::RParent(')'),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            Program::new(vec![
+                Node::FuncDecl(ast::FuncDecl::new(
+                    Token::Ident("add".to_string()),
+                    vec![Token::Ident("a".to_string()), Token::Ident("b".to_string())],
+                    Node::Block(vec![Node::Return(ast::Return::new(Node::Identifier(
+                        Token::Ident("a".to_string())
+                    )))]),
+                )),
+                Node::Call(ast::Call::new(
+                    Node::Identifier(Token::Ident("add".to_string())),
+                    vec![
+                        Node::Literal(Token::Integer(1)),
+                        Node::Literal(Token::Integer(2))
+                    ],
+                )),
+            ]),
+            res.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_group_tuple() {
+        let mut parser = Parser::new(vec![
+            Token::LParent('('),
+            Token::Integer(1),
+            Token::Plus('+'),
+            Token::Integer(2),
+            Token::RParent(')'),
+            Token::Star('*'),
+            Token::Integer(3),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            Program::new(vec![Node::Binary(
+                Rc::new(Node::Group(Rc::new(Node::Binary(
+                    Rc::new(Node::Literal(Token::Integer(1))),
+                    Token::Plus('+'),
+                    Rc::new(Node::Literal(Token::Integer(2))),
+                )))),
+                Token::Star('*'),
+                Rc::new(Node::Literal(Token::Integer(3))),
+            )]),
+            res.unwrap()
+        );
+
+        let mut parser = Parser::new(vec![
+            Token::LParent('('),
+            Token::Integer(1),
+            Token::Comma,
+            Token::RParent(')'),
+            Token::Var,
+            Token::Ident("t".to_string()),
+            Token::Assign('='),
+            Token::LParent('('),
+            Token::Integer(1),
+            Token::Comma,
+            Token::Integer(2),
+            Token::RParent(')'),
+            Token::LSBracket('['),
+            Token::Integer(0),
+            Token::RSBracket(']'),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            Program::new(vec![
+                Node::Tuple(vec![Node::Literal(Token::Integer(1))]),
+                Node::VarStmt(ast::VarStmt::new(
+                    Token::Ident("t".to_string()),
+                    Node::Index(ast::Index::new(
+                        Node::Tuple(vec![
+                            Node::Literal(Token::Integer(1)),
+                            Node::Literal(Token::Integer(2)),
+                        ]),
+                        Node::Literal(Token::Integer(0)),
+                    )),
+                )),
+            ]),
+            res.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_lambda() {
+        let mut parser = Parser::new(vec![
+            Token::Var,
+            Token::Ident("f".to_string()),
+            Token::Assign('='),
+            Token::LParent('('),
+            Token::Ident("x".to_string()),
+            Token::RParent(')'),
+            Token::Arrow,
+            Token::Ident("x".to_string()),
+            Token::Star('*'),
+            Token::Integer(2),
+            Token::Def,
+            Token::LParent('('),
+            Token::RParent(')'),
+            Token::LBrace('{'),
+            Token::RBrace('}'),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            Program::new(vec![
+                Node::VarStmt(ast::VarStmt::new(
+                    Token::Ident("f".to_string()),
+                    Node::Lambda(ast::Lambda::new(
+                        vec![Token::Ident("x".to_string())],
+                        Node::Block(vec![Node::Return(ast::Return::new(Node::Binary(
+                            Rc::new(Node::Identifier(Token::Ident("x".to_string()))),
+                            Token::Star('*'),
+                            Rc::new(Node::Literal(Token::Integer(2))),
+                        )))]),
+                    )),
+                )),
+                Node::Lambda(ast::Lambda::new(vec![], Node::Block(vec![]))),
+            ]),
+            res.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_class() {
+        let mut parser = Parser::new(vec![
+            Token::Class,
+            Token::Ident("Point".to_string()),
+            Token::LBrace('{'),
+            Token::Def,
+            Token::Ident("init".to_string()),
+            Token::LParent('('),
+            Token::Ident("x".to_string()),
+            Token::RParent(')'),
+            Token::LBrace('{'),
+            Token::This,
+            Token::Dot,
+            Token::Ident("x".to_string()),
+            Token::Assign('='),
+            Token::Ident("x".to_string()),
+            Token::RBrace('}'),
+            Token::RBrace('}'),
+            Token::Ident("p".to_string()),
+            Token::Dot,
+            Token::Ident("x".to_string()),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            Program::new(vec![
+                Node::ClassDecl(ast::ClassDecl::new(
+                    Token::Ident("Point".to_string()),
+                    Node::Null,
+                    vec![ast::FuncDecl::new(
+                        Token::Ident("init".to_string()),
+                        vec![Token::Ident("x".to_string())],
+                        Node::Block(vec![Node::Set(ast::Set::new(
+                            Node::This,
+                            Token::Ident("x".to_string()),
+                            Node::Identifier(Token::Ident("x".to_string())),
+                        ))]),
+                    )],
+                )),
+                Node::Get(ast::Get::new(
+                    Node::Identifier(Token::Ident("p".to_string())),
+                    Token::Ident("x".to_string()),
+                )),
+            ]),
+            res.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_subclass() {
+        let mut parser = Parser::new(vec![
+            Token::Class,
+            Token::Ident("Circle".to_string()),
+            Token::Lt("<".to_string()),
+            Token::Ident("Shape".to_string()),
+            Token::LBrace('{'),
+            Token::Def,
+            Token::Ident("area".to_string()),
+            Token::LParent('('),
+            Token::RParent(')'),
+            Token::LBrace('{'),
+            Token::Return,
+            Token::Super,
+            Token::Dot,
+            Token::Ident("area".to_string()),
+            Token::LParent('('),
+            Token::RParent(')'),
+            Token::RBrace('}'),
+            Token::RBrace('}'),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            Program::new(vec![Node::ClassDecl(ast::ClassDecl::new(
+                Token::Ident("Circle".to_string()),
+                Node::Identifier(Token::Ident("Shape".to_string())),
+                vec![ast::FuncDecl::new(
+                    Token::Ident("area".to_string()),
+                    vec![],
+                    Node::Block(vec![Node::Return(ast::Return::new(Node::Call(
+                        ast::Call::new(Node::Super(Token::Ident("area".to_string())), vec![])
+                    )))]),
+                )],
+            ))]),
+            res.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_print() {
+        let mut parser = Parser::new(vec![
+            Token::Print,
+            Token::LParent('('),
+            Token::SString("x =".to_string()),
+            Token::Comma,
+            Token::Ident("x".to_string()),
+            Token::RParent(')'),
+            Token::Print,
+            Token::LParent('('),
+            Token::RParent(')'),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert!(res.is_ok());
+        assert_eq!(
+            Program::new(vec![
+                Node::Print(vec![
+                    Node::Literal(Token::SString("x =".to_string())),
+                    Node::Identifier(Token::Ident("x".to_string())),
+                ]),
+                Node::Print(vec![]),
+            ]),
+            res.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_bitwise_power() {
+        let bin = |l: Node, op: Token, r: Node| Node::Binary(Rc::new(l), op, Rc::new(r));
+        let int = |v: i64| Node::Literal(Token::Integer(v));
+
+        // 1 | 2 ^ 3 & 4 << 5 + 6
+        let mut parser = Parser::new(vec![
+            Token::Integer(1),
+            Token::BitOr,
+            Token::Integer(2),
+            Token::BitXor,
+            Token::Integer(3),
+            Token::BitAnd,
+            Token::Integer(4),
+            Token::ShiftLeft,
+            Token::Integer(5),
+            Token::Plus('+'),
+            Token::Integer(6),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert_eq!(
+            Program::new(vec![bin(
+                int(1),
+                Token::BitOr,
+                bin(
+                    int(2),
+                    Token::BitXor,
+                    bin(
+                        int(3),
+                        Token::BitAnd,
+                        bin(
+                            int(4),
+                            Token::ShiftLeft,
+                            bin(int(5), Token::Plus('+'), int(6))
+                        )
+                    )
+                )
+            )]),
+            res.unwrap()
+        );
+
+        // -2 ** 3 ** 2 % ~5
+        let mut parser = Parser::new(vec![
+            Token::Minus('-'),
+            Token::Integer(2),
+            Token::DoubleStar,
+            Token::Integer(3),
+            Token::DoubleStar,
+            Token::Integer(2),
+            Token::Percent('%'),
+            Token::BitNot,
+            Token::Integer(5),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert_eq!(
+            Program::new(vec![bin(
+                Node::Unary(ast::Unary::new(
+                    Token::Minus('-'),
+                    bin(
+                        int(2),
+                        Token::DoubleStar,
+                        bin(int(3), Token::DoubleStar, int(2))
+                    )
+                )),
+                Token::Percent('%'),
+                Node::Unary(ast::Unary::new(Token::BitNot, int(5)))
+            )]),
+            res.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_assign_targets() {
+        let ident = |s: &str| Token::Ident(s.to_string());
+        let var = |s: &str| Node::Identifier(Token::Ident(s.to_string()));
+
+        // xs[0] = 1 p.x += 2 m["k"] %= 3
+        let mut parser = Parser::new(vec![
+            ident("xs"),
+            Token::LSBracket('['),
+            Token::Integer(0),
+            Token::RSBracket(']'),
+            Token::Assign('='),
+            Token::Integer(1),
+            ident("p"),
+            Token::Dot,
+            ident("x"),
+            Token::PlusAssign,
+            Token::Integer(2),
+            ident("m"),
+            Token::LSBracket('['),
+            Token::SString("k".to_string()),
+            Token::RSBracket(']'),
+            Token::PercentAssign,
+            Token::Integer(3),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert_eq!(
+            Program::new(vec![
+                Node::SetIndex(ast::SetIndex::new(
+                    var("xs"),
+                    Node::Literal(Token::Integer(0)),
+                    Node::Literal(Token::Integer(1)),
+                )),
+                Node::CompoundAssign(ast::CompoundAssign::new(
+                    Node::Get(ast::Get::new(var("p"), ident("x"))),
+                    Token::PlusAssign,
+                    Node::Literal(Token::Integer(2)),
+                )),
+                Node::CompoundAssign(ast::CompoundAssign::new(
+                    Node::Index(ast::Index::new(
+                        var("m"),
+                        Node::Literal(Token::SString("k".to_string())),
+                    )),
+                    Token::PercentAssign,
+                    Node::Literal(Token::Integer(3)),
+                )),
+            ]),
+            res.unwrap()
+        );
+
+        // [a, {b: 1}]
+        let mut parser = Parser::new(vec![
+            Token::LSBracket('['),
+            ident("a"),
+            Token::Comma,
+            Token::LBrace('{'),
+            ident("b"),
+            Token::Colon,
+            Token::Integer(1),
+            Token::RBrace('}'),
+            Token::RSBracket(']'),
+            Token::EOF,
+        ]);
+        let res = parser.parse();
+        println!("parse result: {:?}", res);
+        assert_eq!(
+            Program::new(vec![Node::List(vec![
+                var("a"),
+                Node::Map(vec![(var("b"), Node::Literal(Token::Integer(1)))]),
+            ])]),
+            res.unwrap()
+        );
+
+        // 1 = 2 and a + b -= 1 are not assignable
+        let mut parser = Parser::new(vec![
+            Token::Integer(1),
+            Token::Assign('='),
+            Token::Integer(2),
+            Token::EOF,
+        ]);
+        assert_eq!(
+            "can not assign to: Literal(Integer(1))",
+            parser.parse().unwrap_err().to_string()
+        );
+        let mut parser = Parser::new(vec![
+            ident("a"),
+            Token::Plus('+'),
+            ident("b"),
+            Token::MinusAssign,
+            Token::Integer(1),
+            Token::EOF,
+        ]);
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_spans() {
+        let parse = |input: &str| {
+            let (tokens, spans) = Lexer::with_file(input.to_string(), "t.sip")
+                .scan_tokens_with_spans()
+                .unwrap();
+            Parser::with_spans(tokens, spans).parse()
+        };
+
+        let program = parse("var x = 1\nprint(x + y)").unwrap();
+        let print = match &program.stmts[1] {
+            Node::Spanned(span, inner) => {
+                assert_eq!((2, 1, 12), (span.line, span.column, span.len));
+                (**inner).clone()
+            }
+            node => panic!("expect a spanned statement, got {:?}", node),
+        };
+        let args = match print {
+            Node::Print(args) => args,
+            node => panic!("expect print, got {:?}", node),
+        };
+        // a binary node is placed at its operator, an identifier at its name
+        match &args[0] {
+            Node::Spanned(span, inner) => {
+                assert_eq!((2, 9, 1), (span.line, span.column, span.len));
+                match &**inner {
+                    Node::Binary(_, _, right) => match &**right {
+                        Node::Spanned(span, _) => assert_eq!((2, 11), (span.line, span.column)),
+                        node => panic!("expect a spanned identifier, got {:?}", node),
+                    },
+                    node => panic!("expect binary, got {:?}", node),
+                }
+            }
+            node => panic!("expect a spanned binary, got {:?}", node),
+        }
+
+        let inputs = vec![
+            ("var x = 1\nx + 1 = 2", (2, 7)),
+            ("print(1,\n  2", (2, 4)),
+            ("var x = [1, 2\n  ]]", (2, 4)),
+            ("(a, 1) => a", (1, 8)),
+            ("\"${1 2}\"", (1, 1)),
+        ];
+        for (input, (line, column)) in inputs {
+            let err = parse(input).unwrap_err();
+            println!("{:?} => {:?}", input, err);
+            assert_eq!("t.sip", &*err.span().file);
+            assert_eq!((line, column), (err.span().line, err.span().column));
+        }
+    }
+
+    #[test]
+    fn test_parse_recover() {
+        let input = "var a = 1
+var = 2
+print(a +)
+def f() {
+  var x = )
+  return x
+}
+var b = (1
+print(b)";
+        let (tokens, spans) = Lexer::new(input.to_string())
+            .scan_tokens_with_spans()
+            .unwrap();
+        let (program, errors) = Parser::with_spans(tokens, spans).parse_with_errors();
+        println!("{:?}\n{:?}", program, errors);
+
+        let positions: Vec<_> = errors
+            .iter()
+            .map(|e| (e.span().line, e.span().column))
+            .collect();
+        assert_eq!(vec![(2, 5), (3, 10), (5, 11), (9, 1)], positions);
+        assert_eq!("expect ) after expression", errors[3].to_string());
+
+        // var a, def f with the return left in its body, and print(b)
+        let stmts: Vec<Node> = program
+            .stmts
+            .iter()
+            .map(|n| n.unspanned().clone())
+            .collect();
+        assert_eq!(3, stmts.len());
+        match &stmts[1] {
+            Node::FuncDecl(func) => match &*func.body {
+                Node::Block(body) => assert_eq!(1, body.len()),
+                node => panic!("expect a block, got {:?}", node),
+            },
+            node => panic!("expect a function, got {:?}", node),
+        }
+        assert!(matches!(stmts[2], Node::Print(_)));
+
+        // a { the failed statement opened is skipped up to its }, and a } left over
+        // at the top level is not reported again
+        let inputs = vec![
+            "def f(a {\n  return a\n}\nprint(1)",
+            "while (true { break }\nprint(1)",
+            "if (a { print(1) }\nprint(1)",
+            "class A { var x = 1 }\nprint(1)",
+            "class A {\n  var x = 1\n  def m() { return 1 }\n}\nprint(1)",
+            "var x = (1 + }\nprint(1)",
+        ];
+        for input in inputs {
+            let (tokens, spans) = Lexer::new(input.to_string())
+                .scan_tokens_with_spans()
+                .unwrap();
+            let (program, errors) = Parser::with_spans(tokens, spans).parse_with_errors();
+            println!("{:?} => {:?}", input, errors);
+            assert_eq!(1, errors.len());
+            assert_eq!(1, program.stmts.len());
+            assert!(matches!(program.stmts[0].unspanned(), Node::Print(_)));
+        }
+
+        // without spans there are no lines to stop at, keywords and } still end a
+        // statement, the 3 is skipped along with the stray }
+        let mut parser = Parser::new(vec![
+            Token::Var,
+            Token::Assign('='),
+            Token::Integer(1),
+            Token::Print,
+            Token::LParent('('),
+            Token::Integer(2),
+            Token::RParent(')'),
+            Token::RBrace('}'),
+            Token::Integer(3),
+            Token::EOF,
+        ]);
+        let (program, errors) = parser.parse_with_errors();
+        assert_eq!(
+            Program::new(vec![Node::Print(vec![Node::Literal(Token::Integer(2))])]),
+            program
+        );
+        assert_eq!(2, errors.len());
+
+        // parse still stops at the first error
+        let mut parser = Parser::new(vec![Token::RBrace('}'), Token::Var, Token::EOF]);
+        assert_eq!("RBrace('}')", parser.parse().unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_parse_past_lexer_errors() {
+        let (tokens, spans, errors) =
+            Lexer::new("var a = 1 @ 2\nprint(a)".to_string()).scan_tokens_with_errors();
+        assert_eq!(1, errors.len());
+
+        let (program, errors) = Parser::with_spans(tokens, spans).parse_with_errors();
+        let stmts: Vec<Node> = program
+            .stmts
+            .iter()
+            .map(|n| n.unspanned().clone())
+            .collect();
+        println!("{:?}", stmts);
+        assert_eq!(0, errors.len());
+        assert_eq!(4, stmts.len());
+        assert_eq!(Node::Null, stmts[1]);
+        assert!(matches!(stmts[3], Node::Print(_)));
+
+        // a token the lexer swallowed or could not lex is not reported as missing
+        for input in ["print(\"a ${ n\")", "var @ = 1", "def f(a, @) { return a }"] {
+            let (tokens, spans, errors) = Lexer::new(input.to_string()).scan_tokens_with_errors();
+            assert_eq!(1, errors.len());
+            let (_, errors) = Parser::with_spans(tokens, spans).parse_with_errors();
+            println!("{:?} => {:?}", input, errors);
+            assert!(errors.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_ends() {
+        let parse = |input: &str| {
+            let (tokens, spans) = Lexer::new(input.to_string())
+                .scan_tokens_with_spans()
+                .unwrap();
+            Parser::with_spans(tokens, spans).parse()
+        };
+
+        // (input, how many statements it is)
+        let inputs = vec![
+            ("x = 1\n-2", 2),
+            ("x = 1 -\n2", 1),
+            ("x = (1\n- 2)", 1),
+            ("f\n(1)", 2),
+            ("f(1,\n2)", 1),
+            ("xs\n[0]", 2),
+            ("xs[\n0\n]", 1),
+            ("p\n.x\n.y", 1),
+            ("var a = 1; var b = 2;", 2),
+            (";;var a = 1;;\n;", 1),
+            ("def f() { return 1 } print(f())", 2),
+            ("if (a\n&& b) {\n}\nelse {\n}", 1),
+            ("var s = \"\"\"a\nb\"\"\" + \"c\"", 1),
+            ("print(\n1,\n[2,\n3],\n{\"k\":\n4}\n)", 1),
+            ("for (var i = 0;\ni < 3;\ni += 1) print(i)", 1),
+            ("var f = def () {\n  var a = 1\n  -a\n}", 1),
+        ];
+        for (input, count) in inputs {
+            let res = parse(input);
+            println!("{:?} => {:?}", input, res);
+            assert_eq!(count, res.unwrap().stmts.len());
+        }
+
+        // a block inside brackets still ends its statements at line breaks, and a
+        // return at the end of its line returns nothing
+        let inputs = vec![
+            "f(def () {\n  var a = 1\n  -a\n})",
+            "def f() {\n  return\n  1\n}",
+        ];
+        for input in inputs {
+            let program = parse(input).unwrap();
+            let body = match program.stmts[0].unspanned() {
+                Node::FuncDecl(func) => (*func.body).clone(),
+                Node::Call(call) => match call.args[0].unspanned() {
+                    Node::Lambda(lambda) => (*lambda.body).clone(),
+                    node => panic!("expect a lambda, got {:?}", node),
+                },
+                node => panic!("unexpected {:?}", node),
+            };
+            match body {
+                Node::Block(stmts) => assert_eq!(2, stmts.len()),
+                node => panic!("expect a block, got {:?}", node),
+            }
+        }
+
+        let inputs = vec![
+            (
+                "var a = 1 var b = 2",
+                (1, 11),
+                "expect ; or a new line after statement",
+            ),
+            (
+                "print(1) print(2)",
+                (1, 10),
+                "expect ; or a new line after statement",
+            ),
+            (
+                "{ a = 1 b = 2 }",
+                (1, 9),
+                "expect ; or a new line after statement",
+            ),
+            ("x\n= 1", (2, 1), "Assign('=')"),
+            ("var f = x\n=> x", (2, 1), "Arrow"),
+        ];
+        for (input, (line, column), msg) in inputs {
+            let err = parse(input).unwrap_err();
+            println!("{:?} => {:?}", input, err);
+            assert_eq!((line, column), (err.span().line, err.span().column));
+            assert_eq!(msg, err.to_string());
+        }
+    }
 }
@@ -0,0 +1,64 @@
+use std::fmt;
+use std::rc::Rc;
+
+// where a token or node came from. line and column are 1-based and count chars,
+// offset and len are bytes into the source so the text can be sliced back out
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Span {
+    pub file: Rc<str>,
+    pub line: usize, // 0 when the position is unknown
+    pub column: usize,
+    pub offset: usize,
+    pub len: usize,
+    pub end_line: usize, // the line the span ends on, after the start for multi-line strings
+}
+
+impl Span {
+    pub fn new(file: Rc<str>, line: usize, column: usize, offset: usize, len: usize) -> Self {
+        Self {
+            file,
+            line,
+            column,
+            offset,
+            len,
+            end_line: line,
+        }
+    }
+
+    // tokens built by hand, e.g. in tests, have no position
+    pub fn is_known(&self) -> bool {
+        self.line > 0
+    }
+
+    // from the start of self to the end of other
+    pub fn to(&self, other: &Span) -> Span {
+        if !self.is_known() || !other.is_known() || other.offset < self.offset {
+            return self.clone();
+        }
+
+        let mut span = self.clone();
+        span.len = (other.offset + other.len).max(self.offset + self.len) - self.offset;
+        span.end_line = other.end_line.max(self.end_line);
+        span
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Span;
+
+    #[test]
+    fn test_span_to() {
+        let a = Span::new("a.sip".into(), 1, 5, 4, 3);
+        let b = Span::new("a.sip".into(), 1, 12, 11, 1);
+        assert_eq!(Span::new("a.sip".into(), 1, 5, 4, 8), a.to(&b));
+        assert_eq!(a, a.to(&Span::default()));
+        assert_eq!("a.sip:1:5", a.to_string());
+    }
+}
@@ -1,11 +1,17 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+
 #[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
-    Null,
     WhiteSpace,
     Ident(String),
     Integer(i64),
+    BigInteger(BigInt), // integer literal that does not fit in an i64
     Float(f64),
     SString(String),
+    Interpolated(Vec<StrPart>), // "a ${b} c"
     True,
     False,
 
@@ -14,21 +20,45 @@ pub enum Token {
     If,
     Else,
     For,
+    In, // for x in xs
     While,
+    Break,
+    Continue,
     Return,
-    Def, // def
+    Def,   // def
+    Class, // class
+    This,  // this
+    Super, // super
 
-    Assign(char), // =
-    Plus(char),   // +
-    Minus(char),  // -
-    Star(char),   // *
-    Slash(char),  // /
-    Bang,         // !
+    Assign(char),  // =
+    PlusAssign,    // +=
+    MinusAssign,   // -=
+    StarAssign,    // *=
+    SlashAssign,   // /=
+    PercentAssign, // %=
+    Plus(char),    // +
+    Minus(char),   // -
+    Star(char),    // *
+    DoubleStar,    // ** power
+    Slash(char),   // /
+    TildeSlash,    // ~/ floor division
+    Percent(char), // %
+    Bang,          // !
+    Dot,           // .
+    DotDot,        // ..
+    Semicolon,     // ;
+    Colon,         // :
+    Comma,         // ,
+    Arrow,         // =>
 
-    BitOr,  // |
-    Or,     // ||
-    BitAnd, // &
-    And,    // &&
+    BitOr,      // |
+    Or,         // ||
+    BitAnd,     // &
+    And,        // &&
+    BitXor,     // ^
+    BitNot,     // ~
+    ShiftLeft,  // <<
+    ShiftRight, // >>
 
     LParent(char), // left parenthesis (
     RParent(char), // right parenthesis )
@@ -53,39 +83,69 @@ pub enum Token {
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     Unkown,
-    Null,
     Ident,
     Var,
+    Print,
     Assign,
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    SlashAssign,
+    PercentAssign,
     Or,
     And,
     True,
     False,
     Integer,
+    BigInteger,
     Float,
     String,
+    Interpolated,
     LParent,
     RParent,
-    Lt,    // <
-    LtEQ,  // <=
-    Gt,    // >
-    GtEQ,  // >=
-    EQ,    // ==
-    NotEQ, // !=
-    Slash, // /
-    Star,  // *
-    Plus,  // +
-    Minus, // -
+    Lt,         // <
+    LtEQ,       // <=
+    Gt,         // >
+    GtEQ,       // >=
+    EQ,         // ==
+    NotEQ,      // !=
+    Slash,      // /
+    TildeSlash, // ~/
+    Star,       // *
+    DoubleStar, // **
+    Percent,    // %
+    Plus,       // +
+    Minus,      // -
     // !
     Bang,
+    BitOr,      // |
+    BitAnd,     // &
+    BitXor,     // ^
+    BitNot,     // ~
+    ShiftLeft,  // <<
+    ShiftRight, // >>
     If,
     Else,
     For,
+    In,
     Return,
     While,
+    Break,
+    Continue,
     Def,
+    Class,
+    This,
+    Super,
     LBrace,
     RBrace,
+    LSBracket,
+    RSBracket,
+    Dot,
+    DotDot,
+    Semicolon,
+    Colon,
+    Comma,
+    Arrow,
 }
 
 impl Token {
@@ -93,21 +153,38 @@ impl Token {
         match self {
             Token::Ident(_) => TokenType::Ident,
             Token::Var => TokenType::Var,
+            Token::Print => TokenType::Print,
             Token::Assign(_) => TokenType::Assign,
+            Token::PlusAssign => TokenType::PlusAssign,
+            Token::MinusAssign => TokenType::MinusAssign,
+            Token::StarAssign => TokenType::StarAssign,
+            Token::SlashAssign => TokenType::SlashAssign,
+            Token::PercentAssign => TokenType::PercentAssign,
             Token::Or => TokenType::Or,
             Token::And => TokenType::And,
             Token::True => TokenType::True,
             Token::False => TokenType::False,
             Token::Integer(_) => TokenType::Integer,
+            Token::BigInteger(_) => TokenType::BigInteger,
             Token::Float(_) => TokenType::Float,
             Token::SString(_) => TokenType::String,
+            Token::Interpolated(_) => TokenType::Interpolated,
             Token::LParent(_) => TokenType::LParent,
             Token::RParent(_) => TokenType::RParent,
             Token::Slash(_) => TokenType::Slash,
+            Token::TildeSlash => TokenType::TildeSlash,
             Token::Star(_) => TokenType::Star,
+            Token::DoubleStar => TokenType::DoubleStar,
+            Token::Percent(_) => TokenType::Percent,
             Token::Plus(_) => TokenType::Plus,
             Token::Minus(_) => TokenType::Minus,
             Token::Bang => TokenType::Bang,
+            Token::BitOr => TokenType::BitOr,
+            Token::BitAnd => TokenType::BitAnd,
+            Token::BitXor => TokenType::BitXor,
+            Token::BitNot => TokenType::BitNot,
+            Token::ShiftLeft => TokenType::ShiftLeft,
+            Token::ShiftRight => TokenType::ShiftRight,
             Token::Lt(_) => TokenType::Lt,
             Token::LtEQ(_) => TokenType::LtEQ,
             Token::Gt(_) => TokenType::Gt,
@@ -117,16 +194,110 @@ impl Token {
             Token::If => TokenType::If,
             Token::Else => TokenType::Else,
             Token::For => TokenType::For,
+            Token::In => TokenType::In,
             Token::While => TokenType::While,
+            Token::Break => TokenType::Break,
+            Token::Continue => TokenType::Continue,
             Token::Return => TokenType::Return,
             Token::Def => TokenType::Def,
+            Token::Class => TokenType::Class,
+            Token::This => TokenType::This,
+            Token::Super => TokenType::Super,
             Token::LBrace(_) => TokenType::LBrace,
             Token::RBrace(_) => TokenType::RBrace,
+            Token::LSBracket(_) => TokenType::LSBracket,
+            Token::RSBracket(_) => TokenType::RSBracket,
+            Token::Dot => TokenType::Dot,
+            Token::DotDot => TokenType::DotDot,
+            Token::Semicolon => TokenType::Semicolon,
+            Token::Colon => TokenType::Colon,
+            Token::Comma => TokenType::Comma,
+            Token::Arrow => TokenType::Arrow,
             _ => TokenType::Unkown,
         }
     }
 }
 
+// the token as it is written in the source
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::WhiteSpace => write!(f, " "),
+            Token::Ident(s) => write!(f, "{}", s),
+            Token::Integer(n) => write!(f, "{}", n),
+            Token::BigInteger(n) => write!(f, "{}", n),
+            Token::Float(n) => write!(f, "{}", n),
+            Token::SString(s) => write!(f, "{:?}", s),
+            Token::Interpolated(_) => write!(f, "string"),
+            Token::True => write!(f, "true"),
+            Token::False => write!(f, "false"),
+            Token::Var => write!(f, "var"),
+            Token::Print => write!(f, "print"),
+            Token::If => write!(f, "if"),
+            Token::Else => write!(f, "else"),
+            Token::For => write!(f, "for"),
+            Token::In => write!(f, "in"),
+            Token::While => write!(f, "while"),
+            Token::Break => write!(f, "break"),
+            Token::Continue => write!(f, "continue"),
+            Token::Return => write!(f, "return"),
+            Token::Def => write!(f, "def"),
+            Token::Class => write!(f, "class"),
+            Token::This => write!(f, "this"),
+            Token::Super => write!(f, "super"),
+            Token::Assign(c)
+            | Token::Plus(c)
+            | Token::Minus(c)
+            | Token::Star(c)
+            | Token::Slash(c)
+            | Token::Percent(c)
+            | Token::LParent(c)
+            | Token::RParent(c)
+            | Token::LBrace(c)
+            | Token::RBrace(c)
+            | Token::LSBracket(c)
+            | Token::RSBracket(c) => write!(f, "{}", c),
+            Token::PlusAssign => write!(f, "+="),
+            Token::MinusAssign => write!(f, "-="),
+            Token::StarAssign => write!(f, "*="),
+            Token::SlashAssign => write!(f, "/="),
+            Token::PercentAssign => write!(f, "%="),
+            Token::DoubleStar => write!(f, "**"),
+            Token::TildeSlash => write!(f, "~/"),
+            Token::Bang => write!(f, "!"),
+            Token::Dot => write!(f, "."),
+            Token::DotDot => write!(f, ".."),
+            Token::Semicolon => write!(f, ";"),
+            Token::Colon => write!(f, ":"),
+            Token::Comma => write!(f, ","),
+            Token::Arrow => write!(f, "=>"),
+            Token::BitOr => write!(f, "|"),
+            Token::Or => write!(f, "||"),
+            Token::BitAnd => write!(f, "&"),
+            Token::And => write!(f, "&&"),
+            Token::BitXor => write!(f, "^"),
+            Token::BitNot => write!(f, "~"),
+            Token::ShiftLeft => write!(f, "<<"),
+            Token::ShiftRight => write!(f, ">>"),
+            Token::Lt(s)
+            | Token::LtEQ(s)
+            | Token::Gt(s)
+            | Token::GtEQ(s)
+            | Token::EQ(s)
+            | Token::NotEQ(s) => write!(f, "{}", s),
+            Token::EOF => write!(f, "end of input"),
+            Token::Unkown => write!(f, "unknown token"),
+        }
+    }
+}
+
+// a piece of an interpolated string
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrPart {
+    Text(String),
+    Expr(Vec<Token>), // the tokens between ${ and }, ending with EOF
+}
+
 pub fn get_keyword(kw: &str) -> Option<Token> {
     match kw {
         "true" => Some(Token::True),
@@ -135,10 +306,16 @@ pub fn get_keyword(kw: &str) -> Option<Token> {
         "if" => Some(Token::If),
         "else" => Some(Token::Else),
         "for" => Some(Token::For),
+        "in" => Some(Token::In),
         "while" => Some(Token::While),
+        "break" => Some(Token::Break),
+        "continue" => Some(Token::Continue),
         "return" => Some(Token::Return),
         "print" => Some(Token::Print),
         "def" => Some(Token::Def),
+        "class" => Some(Token::Class),
+        "this" => Some(Token::This),
+        "super" => Some(Token::Super),
         _ => None,
     }
 }